@@ -0,0 +1,157 @@
+use serde::Deserialize;
+use std::fs::OpenOptions;
+use std::io::Write;
+#[cfg(unix)]
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use zf_tools_rs::captcha::StdinSolver;
+use zf_tools_rs::client::ZfClient;
+use zf_tools_rs::session::{SavedSession, SessionBuilder};
+use zf_tools_rs::Result;
+
+/// Write the file readable only by the user, including files created before by others.
+fn write_private(path: &Path, text: &str) -> std::io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    let mut file = options.open(path)?;
+    #[cfg(unix)]
+    file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    file.write_all(text.as_bytes())
+}
+
+/// Credentials and server address, loaded from the config file and overridden by env vars.
+///
+/// ```toml
+/// user = "2018000000"
+/// passwd = "passwd"
+/// base_url = "http://jwxt.sit.edu.cn/jwglxt/"
+/// ```
+#[derive(Debug, Default, Deserialize)]
+pub struct Config {
+    pub user: Option<String>,
+    pub passwd: Option<String>,
+    pub base_url: Option<String>,
+}
+
+impl Config {
+    /// `$XDG_CONFIG_HOME/zf/config.toml` or the equivalent on other platforms
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("zf").join("config.toml"))
+    }
+
+    /// Load the config file if it exists, then apply `ZF_USER`, `ZF_PASSWD` and `ZF_BASE_URL`.
+    pub fn load(path: Option<&Path>) -> std::result::Result<Self, String> {
+        let path = path.map(Path::to_path_buf).or_else(Self::default_path);
+        let mut config = match path {
+            Some(path) if path.exists() => {
+                let text = std::fs::read_to_string(&path)
+                    .map_err(|e| format!("Can't read {}: {}", path.display(), e))?;
+                toml::from_str(&text)
+                    .map_err(|e| format!("Invalid config {}: {}", path.display(), e))?
+            }
+            _ => Config::default(),
+        };
+        if let Ok(user) = std::env::var("ZF_USER") {
+            config.user = Some(user);
+        }
+        if let Ok(passwd) = std::env::var("ZF_PASSWD") {
+            config.passwd = Some(passwd);
+        }
+        if let Ok(base_url) = std::env::var("ZF_BASE_URL") {
+            config.base_url = Some(base_url);
+        }
+        Ok(config)
+    }
+
+    fn user(&self) -> std::result::Result<&str, String> {
+        self.user
+            .as_deref()
+            .ok_or_else(|| "User is required, set ZF_USER or user in the config file.".to_string())
+    }
+
+    /// Session cache of the user, in the cache directory.
+    fn session_path(&self) -> std::result::Result<PathBuf, String> {
+        let dir = dirs::cache_dir().ok_or("Can't find the cache directory.")?;
+        Ok(dir
+            .join("zf")
+            .join(format!("session-{}.json", self.user()?)))
+    }
+
+    fn save_session(&self, client: &ZfClient) -> Result<()> {
+        if let Ok(path) = self.session_path() {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            // The session holds the login cookies.
+            write_private(&path, &client.save().to_json()?)?;
+        }
+        Ok(())
+    }
+
+    /// Where the captcha image is saved for the user to read.
+    fn captcha_path() -> PathBuf {
+        let dir = dirs::cache_dir().map_or_else(std::env::temp_dir, |dir| dir.join("zf"));
+        std::fs::create_dir_all(&dir).ok();
+        dir.join("captcha.jpg")
+    }
+
+    /// Login with the password, and cache the session. The captcha is asked on the terminal if
+    /// the server requires one.
+    pub async fn login(&self) -> std::result::Result<ZfClient, String> {
+        let passwd = self.passwd.as_deref().ok_or(
+            "Password is required, set ZF_PASSWD or passwd in the config file.".to_string(),
+        )?;
+        let mut builder = SessionBuilder::new()
+            .user(self.user()?)
+            .passwd(passwd)
+            .captcha_solver(StdinSolver::new(Self::captcha_path()));
+        if let Some(base_url) = &self.base_url {
+            builder = builder.base_url(base_url);
+        }
+        let client = builder.build().login().await.map_err(|e| e.to_string())?;
+        self.save_session(&client).map_err(|e| e.to_string())?;
+        Ok(client)
+    }
+
+    /// Use the cached session if it is still valid, or login again.
+    pub async fn client(&self) -> std::result::Result<ZfClient, String> {
+        let saved = self
+            .session_path()
+            .ok()
+            .and_then(|path| SavedSession::load_from_file(path).ok())
+            .filter(|saved| {
+                self.base_url.as_ref().map_or(true, |base_url| {
+                    saved.base_url.trim_end_matches('/') == base_url.trim_end_matches('/')
+                })
+            });
+        if let Some(saved) = saved {
+            let mut builder = SessionBuilder::from_saved(saved);
+            if let Some(passwd) = &self.passwd {
+                builder = builder.passwd(passwd);
+            }
+            let client = builder.build().restore();
+            if client.is_valid().await.unwrap_or(false) {
+                return Ok(client);
+            }
+        }
+        self.login().await
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_write_private() {
+    let path = std::env::temp_dir().join(format!("zf-session-{}.json", std::process::id()));
+    std::fs::write(&path, "{}").unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+    write_private(&path, "{\"user\": \"2018000000\"}").unwrap();
+    let metadata = std::fs::metadata(&path).unwrap();
+    assert_eq!(metadata.permissions().mode() & 0o777, 0o600);
+    assert!(std::fs::read_to_string(&path)
+        .unwrap()
+        .contains("2018000000"));
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,234 @@
+mod config;
+mod output;
+
+use clap::{Parser, Subcommand, ValueEnum};
+use config::Config;
+use output::{render, Format};
+use serde::Serialize;
+use std::path::PathBuf;
+use zf_tools_rs::client::{Environment, User, ZfClient};
+use zf_tools_rs::gpa::{FourPointScale, GpaCalculator, Pku, RetakePolicy, SitStandard, Wes};
+use zf_tools_rs::parsers::{SchoolYear, Semester};
+use zf_tools_rs::ZfError;
+
+/// Command line tool for 正方教务系统.
+///
+/// Credentials are read from `ZF_USER`, `ZF_PASSWD` and `ZF_BASE_URL`, or the config file, and the
+/// session is cached so that it logs in only when expired.
+#[derive(Parser)]
+#[command(name = "zf", version)]
+struct Cli {
+    /// Config file, defaults to zf/config.toml in the config directory
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+    #[arg(short, long, global = true, value_enum, default_value = "table")]
+    format: Format,
+    #[command(subcommand)]
+    command: Command,
+}
+
+/// School year and semester to query
+#[derive(clap::Args)]
+struct Term {
+    /// First year of the school year, like 2020 for 2020-2021. All years if omitted
+    #[arg(short, long)]
+    year: Option<i32>,
+    #[arg(short, long, value_enum, default_value = "all")]
+    semester: SemesterArg,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum SemesterArg {
+    All,
+    First,
+    Second,
+    Mid,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Strategy {
+    Sit,
+    FourPoint,
+    Wes,
+    Pku,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Retake {
+    All,
+    Best,
+    Latest,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Login and cache the session
+    Login,
+    /// Personal profile
+    Profile,
+    /// Courses in the timetable
+    Timetable(Term),
+    /// Score list
+    Scores(Term),
+    /// GPA of each term and the cumulative one
+    Gpa {
+        #[command(flatten)]
+        term: Term,
+        #[arg(long, value_enum, default_value = "sit")]
+        strategy: Strategy,
+        #[arg(long, value_enum, default_value = "all")]
+        retake: Retake,
+        /// Only count required courses
+        #[arg(long)]
+        required_only: bool,
+    },
+    /// Exam arrangement
+    Exams(Term),
+    /// Majors of an entrance year
+    Majors {
+        /// Entrance year, like 2018
+        entrance_year: i32,
+    },
+    /// Classes in the school
+    Classes(Term),
+    /// Suggested courses of a class
+    Suggested {
+        #[command(flatten)]
+        term: Term,
+        /// Major id, see `zf majors`
+        #[arg(long)]
+        major: String,
+        /// Class id, see `zf classes`
+        #[arg(long)]
+        class: String,
+        /// Entrance year, guessed from the class id if omitted
+        #[arg(long)]
+        entrance_year: Option<String>,
+    },
+}
+
+impl Term {
+    fn year(&self) -> SchoolYear {
+        match self.year {
+            Some(year) => SchoolYear::SomeYear(year),
+            None => SchoolYear::AllYear,
+        }
+    }
+
+    fn semester(&self) -> Semester {
+        match self.semester {
+            SemesterArg::All => Semester::All,
+            SemesterArg::First => Semester::FirstTerm,
+            SemesterArg::Second => Semester::SecondTerm,
+            SemesterArg::Mid => Semester::MidTerm,
+        }
+    }
+}
+
+fn calculator(strategy: Strategy, retake: Retake, required_only: bool) -> GpaCalculator {
+    let calculator = match strategy {
+        Strategy::Sit => GpaCalculator::new(SitStandard),
+        Strategy::FourPoint => GpaCalculator::new(FourPointScale),
+        Strategy::Wes => GpaCalculator::new(Wes),
+        Strategy::Pku => GpaCalculator::new(Pku),
+    };
+    let retake = match retake {
+        Retake::All => RetakePolicy::All,
+        Retake::Best => RetakePolicy::Best,
+        Retake::Latest => RetakePolicy::Latest,
+    };
+    calculator.retake(retake).required_only(required_only)
+}
+
+fn error(e: ZfError) -> String {
+    e.to_string()
+}
+
+fn print<T: Serialize>(value: &T, format: Format) -> Result<(), String> {
+    println!("{}", render(value, format)?);
+    Ok(())
+}
+
+async fn run(client: &ZfClient, command: Command, format: Format) -> Result<(), String> {
+    match command {
+        Command::Login => println!("Logged in as {}.", client.user()),
+        Command::Profile => print(&client.get_profile().await.map_err(error)?, format)?,
+        Command::Timetable(term) => {
+            let courses = client.get_timetable(term.year(), term.semester()).await;
+            print(&courses.map_err(error)?, format)?
+        }
+        Command::Scores(term) => {
+            let scores = client.get_score_list(term.year(), term.semester()).await;
+            print(&scores.map_err(error)?, format)?
+        }
+        Command::Gpa {
+            term,
+            strategy,
+            retake,
+            required_only,
+        } => {
+            let calculator = calculator(strategy, retake, required_only);
+            let report = client
+                .get_gpa_report(term.year(), term.semester(), &calculator)
+                .await
+                .map_err(error)?;
+            if format == Format::Json {
+                return print(&report, format);
+            }
+            print(&report.terms, format)?;
+            match report.cumulative {
+                Some(gpa) => println!("\nGPA: {:.2}, credits: {}", gpa, report.credits),
+                None => println!("\nNo course is counted."),
+            }
+        }
+        Command::Exams(term) => {
+            let exams = client.get_exam_list(term.year(), term.semester()).await;
+            print(&exams.map_err(error)?, format)?
+        }
+        Command::Majors { entrance_year } => {
+            let majors = client
+                .get_major_list(SchoolYear::SomeYear(entrance_year))
+                .await;
+            print(&majors.map_err(error)?, format)?
+        }
+        Command::Classes(term) => {
+            let classes = client.get_class_list(term.year(), term.semester()).await;
+            print(&classes.map_err(error)?, format)?
+        }
+        Command::Suggested {
+            term,
+            major,
+            class,
+            entrance_year,
+        } => {
+            let courses = client
+                .get_suggested_course_list(
+                    term.year(),
+                    term.semester(),
+                    &major,
+                    &class,
+                    entrance_year.as_deref(),
+                )
+                .await;
+            print(&courses.map_err(error)?, format)?
+        }
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+    let result = async {
+        let config = Config::load(cli.config.as_deref())?;
+        let client = match cli.command {
+            Command::Login => config.login().await?,
+            _ => config.client().await?,
+        };
+        run(&client, cli.command, cli.format).await
+    };
+    if let Err(e) = result.await {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
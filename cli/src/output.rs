@@ -0,0 +1,109 @@
+use clap::ValueEnum;
+use serde::Serialize;
+use serde_json::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    Table,
+    Json,
+    Csv,
+}
+
+/// Text of a cell: strings as they are, lists joined by comma, and objects in JSON.
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        Value::Array(list) => list.iter().map(cell).collect::<Vec<_>>().join(","),
+        value => value.to_string(),
+    }
+}
+
+/// Header and rows of the value, each object in a list is a row.
+fn to_rows(value: &Value) -> (Vec<String>, Vec<Vec<String>>) {
+    let objects: Vec<&serde_json::Map<String, Value>> = match value {
+        Value::Array(list) => list.iter().filter_map(Value::as_object).collect(),
+        Value::Object(object) => vec![object],
+        _ => vec![],
+    };
+    let header: Vec<String> = match objects.first() {
+        Some(object) => object.keys().cloned().collect(),
+        None => return (vec![], vec![]),
+    };
+    let rows = objects
+        .iter()
+        .map(|object| {
+            header
+                .iter()
+                .map(|key| object.get(key).map(cell).unwrap_or_default())
+                .collect()
+        })
+        .collect();
+    (header, rows)
+}
+
+/// Display width, CJK characters take two columns.
+fn width(s: &str) -> usize {
+    s.chars().map(|c| if c >= '\u{1100}' { 2 } else { 1 }).sum()
+}
+
+fn render_table(header: &[String], rows: &[Vec<String>]) -> String {
+    let mut widths: Vec<usize> = header.iter().map(|h| width(h)).collect();
+    for row in rows {
+        for (w, text) in widths.iter_mut().zip(row) {
+            *w = std::cmp::max(*w, width(text));
+        }
+    }
+
+    let line = |cells: &[String]| {
+        cells
+            .iter()
+            .zip(&widths)
+            .map(|(text, w)| format!("{}{}", text, " ".repeat(w - width(text))))
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+    let mut output = vec![line(header)];
+    output.extend(rows.iter().map(|row| line(row)));
+    output.join("\n")
+}
+
+fn render_csv(header: &[String], rows: &[Vec<String>]) -> Result<String, String> {
+    let mut writer = csv::Writer::from_writer(vec![]);
+    let records = std::iter::once(header).chain(rows.iter().map(Vec::as_slice));
+    for record in records {
+        writer.write_record(record).map_err(|e| e.to_string())?;
+    }
+    let data = writer.into_inner().map_err(|e| e.to_string())?;
+    String::from_utf8(data).map_err(|e| e.to_string())
+}
+
+pub fn render<T: Serialize>(value: &T, format: Format) -> Result<String, String> {
+    let value = serde_json::to_value(value).map_err(|e| e.to_string())?;
+    if format == Format::Json {
+        return serde_json::to_string_pretty(&value).map_err(|e| e.to_string());
+    }
+    let (header, rows) = to_rows(&value);
+    match format {
+        Format::Csv => render_csv(&header, &rows),
+        _ => Ok(render_table(&header, &rows)),
+    }
+}
+
+#[test]
+fn test_render() {
+    let value = serde_json::json!([
+        {"course": "大学物理", "weeks": [1, 2, 3], "credit": 4.0},
+        {"course": "English", "weeks": [], "credit": 2.5},
+    ]);
+    assert_eq!(
+        render(&value, Format::Table).unwrap(),
+        "course    credit  weeks\n大学物理  4.0     1,2,3\nEnglish   2.5"
+    );
+    assert_eq!(
+        render(&value, Format::Csv).unwrap(),
+        "course,credit,weeks\n大学物理,4.0,\"1,2,3\"\nEnglish,2.5,\n"
+    );
+}
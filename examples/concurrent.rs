@@ -0,0 +1,15 @@
+use zf_tools_rs::client::User;
+use zf_tools_rs::parsers::{SchoolYear, Semester};
+use zf_tools_rs::session::SessionBuilder;
+
+#[tokio::main]
+async fn main() {
+    let session = SessionBuilder::new().user("user").passwd("passwd").build();
+    let client = session.login().await.unwrap();
+    let (profile, scores, timetable) = tokio::join!(
+        client.get_profile(),
+        client.get_score_list(SchoolYear::AllYear, Semester::All),
+        client.get_timetable(SchoolYear::SomeYear(2020), Semester::FirstTerm),
+    );
+    println!("{:?}\n{:?}\n{:?}", profile, scores, timetable);
+}
@@ -0,0 +1,20 @@
+use zf_tools_rs::client::CourseSelection;
+use zf_tools_rs::session::SessionBuilder;
+
+#[tokio::main]
+async fn main() {
+    let session = SessionBuilder::new().user("user").passwd("passwd").build();
+    let x = session.login().await;
+    match x {
+        Ok(y) => {
+            let round = y.get_selection_round().await.unwrap();
+            for course_type in round.course_types() {
+                let course_list = y.get_available_course_list(&round, course_type).await;
+                println!("{}: {:?}", course_type.name(), course_list);
+            }
+        }
+        _ => {
+            println!("error")
+        }
+    }
+}
@@ -0,0 +1,19 @@
+use zf_tools_rs::client::User;
+use zf_tools_rs::parsers::{SchoolYear, Semester};
+use zf_tools_rs::session::SessionBuilder;
+
+#[tokio::main]
+async fn main() {
+    let session = SessionBuilder::new().user("user").passwd("passwd").build();
+    let x = session.login().await;
+    match x {
+        Ok(y) => {
+            let m = SchoolYear::SomeYear(2020);
+            let exam = y.get_exam_list(m, Semester::All).await;
+            println!("{:?}", exam);
+        }
+        _ => {
+            println!("error")
+        }
+    }
+}
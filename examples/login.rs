@@ -2,7 +2,7 @@ use zf_tools_rs::session::SessionBuilder;
 
 #[tokio::main]
 async fn main() {
-    let mut session = SessionBuilder::new().user("user").passwd("passwd").build();
+    let session = SessionBuilder::new().user("user").passwd("passwd").build();
     let x = session.login().await;
     println!("{:?}", x);
 }
@@ -1,13 +1,12 @@
 use zf_tools_rs::client::User;
-use zf_tools_rs::parsers::{SchoolYear, Semester};
 use zf_tools_rs::session::SessionBuilder;
 
 #[tokio::main]
 async fn main() {
-    let mut session = SessionBuilder::new().user("user").passwd("passwd").build();
+    let session = SessionBuilder::new().user("user").passwd("passwd").build();
     let x = session.login().await;
     match x {
-        Ok(mut y) => {
+        Ok(y) => {
             let profile = y.get_profile().await;
             println!("{:?}", profile);
         }
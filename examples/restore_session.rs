@@ -0,0 +1,22 @@
+use zf_tools_rs::client::User;
+use zf_tools_rs::session::{SavedSession, SessionBuilder};
+
+#[tokio::main]
+async fn main() {
+    let path = "session.json";
+    let client = match SavedSession::load_from_file(path) {
+        Ok(saved) => SessionBuilder::from_saved(saved)
+            .passwd("passwd")
+            .build()
+            .restore(),
+        Err(_) => {
+            let session = SessionBuilder::new().user("user").passwd("passwd").build();
+            session.login().await.unwrap()
+        }
+    };
+    println!("valid: {:?}", client.is_valid().await);
+
+    let profile = client.get_profile().await;
+    println!("{:?}", profile);
+    client.save().save_to_file(path).unwrap();
+}
@@ -0,0 +1,226 @@
+use crate::parsers::{Course, PeriodRange, WeekSet, Weekday};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::ops::RangeInclusive;
+
+/// Two courses which take place at the same time
+#[derive(Debug, Clone, Serialize)]
+pub struct Conflict<'a> {
+    pub first: &'a Course,
+    pub second: &'a Course,
+    pub day: Weekday,
+    /// Weeks both courses take place
+    pub weeks: WeekSet,
+    /// Periods both courses take place
+    pub periods: PeriodRange,
+}
+
+/// Continuous free periods in a day
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FreeSlot {
+    pub week: u32,
+    pub day: Weekday,
+    pub periods: PeriodRange,
+}
+
+/// Find overlapping courses (same week, day and period) in all the given timetables, like a
+/// student's timetable and the courses planned to select.
+///
+/// A teaching class appearing in more than one timetable is only counted once, and meetings of
+/// the same teaching class are not regarded as conflicts of each other.
+pub fn find_conflicts(timetables: &[Vec<Course>]) -> Vec<Conflict<'_>> {
+    // Each meeting of a class is an entry, so entries are the same only if all of them match.
+    let mut entries = HashSet::new();
+    let courses: Vec<&Course> = timetables
+        .iter()
+        .flatten()
+        .filter(|course| {
+            course.dyn_class_id.is_empty()
+                || entries.insert((
+                    &course.dyn_class_id,
+                    course.day,
+                    course.periods,
+                    course.weeks,
+                ))
+        })
+        .collect();
+    let mut conflicts = Vec::new();
+
+    for (i, first) in courses.iter().enumerate() {
+        for second in &courses[i + 1..] {
+            if first.dyn_class_id == second.dyn_class_id && !first.dyn_class_id.is_empty() {
+                continue;
+            }
+            let (day, p0, p1) = match (first.day, first.periods, second.day, second.periods) {
+                (Some(d0), Some(p0), Some(d1), Some(p1)) if d0 == d1 && p0.overlaps(&p1) => {
+                    (d0, p0, p1)
+                }
+                _ => continue,
+            };
+            let weeks = first.weeks.intersection(&second.weeks);
+            if weeks.is_empty() {
+                continue;
+            }
+            conflicts.push(Conflict {
+                first,
+                second,
+                day,
+                weeks,
+                periods: PeriodRange {
+                    start: std::cmp::max(p0.start, p1.start),
+                    end: std::cmp::min(p0.end, p1.end),
+                },
+            });
+        }
+    }
+    conflicts
+}
+
+/// Periods in a day are kept in a `u64` bitset, so that at most 63 periods are supported.
+const MAX_PERIODS: i32 = 63;
+
+/// Bitset of the periods in the range, periods out of 1 to `MAX_PERIODS` are ignored.
+fn period_bits(periods: PeriodRange) -> u64 {
+    let start = std::cmp::max(periods.start, 1);
+    let end = std::cmp::min(periods.end, MAX_PERIODS);
+    (start..=end).fold(0, |bits, index| bits | 1 << index)
+}
+
+/// Find periods in which nobody in the given timetables has a course, for each day of the weeks.
+///
+/// `periods_per_day` is the number of periods in a day, like 11 for SIT, and at most 63.
+pub fn find_free_slots(
+    timetables: &[Vec<Course>],
+    weeks: RangeInclusive<u32>,
+    periods_per_day: i32,
+) -> Vec<FreeSlot> {
+    let periods_per_day = std::cmp::min(periods_per_day, MAX_PERIODS);
+    // Occupied periods of each (week, day), as a bitset
+    let mut occupied: HashMap<(u32, Weekday), u64> = HashMap::new();
+    for course in timetables.iter().flatten() {
+        let (day, periods) = match (course.day, course.periods) {
+            (Some(day), Some(periods)) => (day, periods),
+            _ => continue,
+        };
+        for week in course.weeks.iter() {
+            *occupied.entry((week, day)).or_default() |= period_bits(periods);
+        }
+    }
+
+    let mut slots = Vec::new();
+    for week in weeks {
+        for day in Weekday::all() {
+            let bits = occupied.get(&(week, day)).copied().unwrap_or_default();
+            let mut start = None;
+            for index in 1..=periods_per_day + 1 {
+                let busy = index > periods_per_day || bits & (1u64 << index) != 0;
+                match (busy, start) {
+                    (false, None) => start = Some(index),
+                    (true, Some(s)) => {
+                        slots.push(FreeSlot {
+                            week,
+                            day,
+                            periods: PeriodRange {
+                                start: s,
+                                end: index - 1,
+                            },
+                        });
+                        start = None;
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+    slots
+}
+
+#[test]
+fn test_find_conflicts() {
+    use crate::parsers::parse_timetable_page;
+
+    let timetable = parse_timetable_page(
+        r#"{"kbList": [
+            {"jcs": "1-2", "jxbmc": "A", "kch": "A", "kcmc": "A", "xf": "2.0", "xqjmc": "星期一", "zcd": "1-16周", "zxs": "32"},
+            {"jcs": "3-4", "jxbmc": "B", "kch": "B", "kcmc": "B", "xf": "2.0", "xqjmc": "星期一", "zcd": "1-16周", "zxs": "32"}
+        ]}"#,
+    )
+    .unwrap();
+    let planned = parse_timetable_page(
+        r#"{"kbList": [
+            {"jcs": "2-3", "jxbmc": "C", "kch": "C", "kcmc": "C", "xf": "2.0", "xqjmc": "星期一", "zcd": "1-15周(单)", "zxs": "32"},
+            {"jcs": "1-2", "jxbmc": "D", "kch": "D", "kcmc": "D", "xf": "2.0", "xqjmc": "星期一", "zcd": "2-16周(双)", "zxs": "32"},
+            {"jcs": "1-2", "jxbmc": "A", "kch": "A", "kcmc": "A", "xf": "2.0", "xqjmc": "星期一", "zcd": "1-16周", "zxs": "32"}
+        ]}"#,
+    )
+    .unwrap();
+    let timetables = [timetable, planned];
+    let conflicts = find_conflicts(&timetables);
+
+    // Class A is in both timetables, but its conflicts are only reported once.
+    let pairs: Vec<(&str, &str)> = conflicts
+        .iter()
+        .map(|c| (c.first.course_id.as_str(), c.second.course_id.as_str()))
+        .collect();
+    assert_eq!(pairs, vec![("A", "C"), ("A", "D"), ("B", "C")]);
+    assert_eq!(conflicts[0].periods, PeriodRange { start: 2, end: 2 });
+    assert!(conflicts[0].weeks.is_odd_only());
+
+    // Class E meets twice a week, and only its meeting on Wednesday clashes with F.
+    let timetable = parse_timetable_page(
+        r#"{"kbList": [
+            {"jcs": "1-2", "jxbmc": "E", "kch": "E", "kcmc": "E", "xf": "4.0", "xqjmc": "星期一", "zcd": "1-16周", "zxs": "64"},
+            {"jcs": "3-4", "jxbmc": "E", "kch": "E", "kcmc": "E", "xf": "4.0", "xqjmc": "星期三", "zcd": "1-16周", "zxs": "64"}
+        ]}"#,
+    )
+    .unwrap();
+    let planned = parse_timetable_page(
+        r#"{"kbList": [
+            {"jcs": "4-5", "jxbmc": "F", "kch": "F", "kcmc": "F", "xf": "2.0", "xqjmc": "星期三", "zcd": "1-8周", "zxs": "32"}
+        ]}"#,
+    )
+    .unwrap();
+    let timetables = [timetable, planned];
+    let conflicts = find_conflicts(&timetables);
+    assert_eq!(conflicts.len(), 1);
+    assert_eq!(conflicts[0].day, Weekday::Wednesday);
+    assert_eq!(conflicts[0].periods, PeriodRange { start: 4, end: 4 });
+}
+
+#[test]
+fn test_find_free_slots() {
+    use crate::parsers::parse_timetable_page;
+
+    let timetables = [
+        parse_timetable_page(
+            r#"{"kbList": [
+                {"jcs": "1-2", "jxbmc": "A", "kch": "A", "kcmc": "A", "xf": "2.0", "xqjmc": "星期一", "zcd": "1-16周", "zxs": "32"}
+            ]}"#,
+        )
+        .unwrap(),
+        parse_timetable_page(
+            r#"{"kbList": [
+                {"jcs": "5-6", "jxbmc": "B", "kch": "B", "kcmc": "B", "xf": "2.0", "xqjmc": "星期一", "zcd": "1-15周(单)", "zxs": "32"},
+                {"jcs": "60-80", "jxbmc": "E", "kch": "E", "kcmc": "E", "xf": "2.0", "xqjmc": "星期二", "zcd": "1-16周", "zxs": "32"}
+            ]}"#,
+        )
+        .unwrap(),
+    ];
+    let slots = find_free_slots(&timetables, 1..=2, 8);
+
+    let monday: Vec<&FreeSlot> = slots.iter().filter(|s| s.day == Weekday::Monday).collect();
+    assert_eq!(
+        monday
+            .iter()
+            .map(|s| (s.week, s.periods.start, s.periods.end))
+            .collect::<Vec<_>>(),
+        vec![(1, 3, 4), (1, 7, 8), (2, 3, 8)]
+    );
+    assert_eq!(slots.len(), 3 + 6 * 2);
+
+    // Periods out of the bitset do not overflow.
+    let slots = find_free_slots(&timetables, 1..=1, 100);
+    let tuesday: Vec<&FreeSlot> = slots.iter().filter(|s| s.day == Weekday::Tuesday).collect();
+    assert_eq!(tuesday.len(), 1);
+    assert_eq!(tuesday[0].periods, PeriodRange { start: 1, end: 59 });
+}
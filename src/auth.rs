@@ -0,0 +1,350 @@
+//! Ways to login, see `SessionBuilder::authenticator`.
+//!
+//! `DirectLogin` posts the login form of 正方 itself, which is the default. `CasLogin` logs in a
+//! CAS server (unified authentication, 统一身份认证), which redirects to 正方 with a ticket.
+
+use crate::captcha;
+use crate::config::url;
+use crate::session::{encrypt_in_rsa, Session};
+use crate::{Result, ZfError};
+use aes::cipher::generic_array::GenericArray;
+use aes::cipher::{BlockEncrypt, KeyInit};
+use aes::Aes128;
+use async_trait::async_trait;
+use base64::encode;
+use rand::Rng;
+use reqwest::{Response, Url};
+use scraper::{Html, Selector};
+use std::fmt;
+
+/// Login the session with its user and password.
+#[async_trait]
+pub trait Authenticator: Send + Sync + fmt::Debug {
+    /// Login and keep the cookies in the session. It fails unless 正方 has accepted the login.
+    async fn login(&self, session: &Session) -> Result<()>;
+}
+
+/// The login form of 正方, with the password encrypted by the RSA public key of the server.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DirectLogin;
+
+/// Error message on the login page, `None` if there is no one.
+fn parse_err_message(content: &str, selector: &str) -> Option<String> {
+    let document = Html::parse_document(content);
+    let selector = Selector::parse(selector).expect("Invalid error message selector");
+    let err_node: String = document.select(&selector).next()?.text().collect();
+    Some(err_node.trim().to_string()).filter(|message| !message.is_empty())
+}
+
+/// Error of the login page shown again after posting the form.
+async fn login_error(response: Response, selector: &str, captcha: bool) -> Result<ZfError> {
+    let text = response.text().await?;
+    let message = parse_err_message(&text, selector).unwrap_or_else(|| "unknown".to_string());
+    if message.contains("验证码") {
+        return Ok(if captcha {
+            ZfError::CaptchaRejected(message)
+        } else {
+            ZfError::CaptchaRequired
+        });
+    }
+    Ok(ZfError::AuthenticationFailed(message))
+}
+
+fn check_status(response: &Response) -> Result<()> {
+    if !response.status().is_success() {
+        return Err(ZfError::HttpStatus {
+            status: response.status(),
+            url: response.url().to_string(),
+        });
+    }
+    Ok(())
+}
+
+#[async_trait]
+impl Authenticator for DirectLogin {
+    async fn login(&self, session: &Session) -> Result<()> {
+        let login_url = session.url(url::LOGIN)?;
+        let request = session.client.get(login_url.clone());
+        let login_page = session.execute(request).await?;
+
+        let text = login_page.text().await?;
+        let token = session.get_csrf_token(&text)?;
+        let captcha = if captcha::is_required(&text) {
+            Some(session.solve_captcha(session.url(url::CAPTCHA)?).await?)
+        } else {
+            None
+        };
+
+        let (public_key, exponent) = session.get_ras_public_key().await?;
+        let encrypted_passwd = encrypt_in_rsa(session.passwd()?.as_bytes(), public_key, exponent)?;
+
+        let mut params = vec![
+            ("csrftoken", token.as_str()),
+            ("language", "zh_CN"),
+            ("yhm", session.user.as_str()),
+            ("mm", encrypted_passwd.as_str()),
+        ];
+        if let Some(answer) = &captcha {
+            params.push(("yzm", answer.as_str()));
+        }
+
+        let final_response = session.post_with_auto_redirect(login_url, &params).await?;
+        if session.is_login_page(&final_response) {
+            let selector = "div#home.tab-pane.in.active p#tips.bg_danger.sl_danger";
+            return Err(login_error(final_response, selector, captcha.is_some()).await?);
+        }
+        check_status(&final_response)
+    }
+}
+
+/// Characters of the random text in the encrypted password, the same as the CAS page script.
+const AES_CHARS: &[u8] = b"ABCDEFGHJKMNPQRSTWXYZabcdefhijkmnprstwxyz2345678";
+
+fn random_text(len: usize) -> Vec<u8> {
+    let mut rng = rand::thread_rng();
+    (0..len)
+        .map(|_| AES_CHARS[rng.gen_range(0..AES_CHARS.len())])
+        .collect()
+}
+
+/// AES-128-CBC with PKCS#7 padding.
+fn encrypt_cbc(data: &[u8], key: &[u8], iv: &[u8]) -> Result<Vec<u8>> {
+    if key.len() != 16 || iv.len() != 16 {
+        return Err(ZfError::Encryption(
+            "AES key and IV should be 16 bytes".to_string(),
+        ));
+    }
+    let cipher = Aes128::new(GenericArray::from_slice(key));
+    let padding = 16 - data.len() % 16;
+    let mut data = data.to_vec();
+    data.resize(data.len() + padding, padding as u8);
+
+    let mut last = GenericArray::clone_from_slice(iv);
+    let mut output = Vec::with_capacity(data.len());
+    for block in data.chunks(16) {
+        let mut block = GenericArray::clone_from_slice(block);
+        block.iter_mut().zip(&last).for_each(|(a, b)| *a ^= b);
+        cipher.encrypt_block(&mut block);
+        output.extend_from_slice(&block);
+        last = block;
+    }
+    Ok(output)
+}
+
+/// Encrypt the password with the salt on the CAS login page, like the page script does: 64
+/// random characters and the password, in AES-128-CBC with the salt as the key and a random IV.
+pub fn encrypt_password(passwd: &str, salt: &str) -> Result<String> {
+    let mut data = random_text(64);
+    data.extend_from_slice(passwd.as_bytes());
+    let encrypted = encrypt_cbc(&data, salt.trim().as_bytes(), &random_text(16))?;
+    Ok(encode(encrypted))
+}
+
+/// Login form on the CAS login page
+#[derive(Debug, Default)]
+struct CasForm {
+    action: Option<String>,
+    /// Hidden fields, like `lt`, `execution` and `_eventId`
+    fields: Vec<(String, String)>,
+    /// Salt of the password, `None` if the password is sent as it is
+    salt: Option<String>,
+    captcha: bool,
+}
+
+fn parse_cas_form(page: &str) -> Result<CasForm> {
+    let document = Html::parse_document(page);
+    let selector = |s: &str| Selector::parse(s).expect("Invalid CAS form selector");
+
+    let form = document
+        .select(&selector("form"))
+        .find(|form| {
+            form.select(&selector("input[name=execution]"))
+                .next()
+                .is_some()
+        })
+        .ok_or_else(|| ZfError::parse("execution", "no login form").at("login"))?;
+    let fields = form
+        .select(&selector("input[type=hidden][name]"))
+        .filter_map(|input| {
+            let name = input.value().attr("name")?;
+            let value = input.value().attr("value").unwrap_or_default();
+            Some((name.to_string(), value.to_string()))
+        })
+        .collect();
+    let salt = document
+        .select(&selector(
+            "input#pwdDefaultEncryptSalt, input#pwdEncryptSalt",
+        ))
+        .find_map(|input| input.value().attr("value"))
+        .filter(|salt| !salt.is_empty())
+        .map(ToString::to_string);
+    let captcha = document
+        .select(&selector("input#captchaResponse"))
+        .next()
+        .is_some();
+
+    Ok(CasForm {
+        action: form.value().attr("action").map(ToString::to_string),
+        fields,
+        salt,
+        captcha,
+    })
+}
+
+/// Unified authentication through a CAS server, like `https://authserver.sit.edu.cn/authserver/`.
+///
+/// It posts the login form of the CAS server with the `lt` and `execution` tokens, and the
+/// password salted and encrypted if the page asks so. The CAS server redirects to the service of
+/// 正方 with a ticket, where 正方 creates the session.
+#[derive(Debug, Clone)]
+pub struct CasLogin {
+    /// CAS server address, always ends with `/`
+    server: Url,
+    service: Option<String>,
+}
+
+impl CasLogin {
+    pub fn new(server: &str) -> Result<Self> {
+        let mut server = Url::parse(server)?;
+        if !server.path().ends_with('/') {
+            server.set_path(&format!("{}/", server.path()));
+        }
+        Ok(CasLogin {
+            server,
+            service: None,
+        })
+    }
+
+    /// Service URL of 正方 registered on the CAS server, defaults to `sso/jziotlogin` under the
+    /// base URL of the session as on SIT. Other schools may register another one.
+    pub fn service<T: ToString>(mut self, service: T) -> Self {
+        self.service = Some(service.to_string());
+        self
+    }
+}
+
+#[async_trait]
+impl Authenticator for CasLogin {
+    async fn login(&self, session: &Session) -> Result<()> {
+        let service = match &self.service {
+            Some(service) => Url::parse(service)?,
+            None => session.base_url.join(url::CAS_SERVICE)?,
+        };
+        let mut login_url = self.server.join("login")?;
+        login_url
+            .query_pairs_mut()
+            .append_pair("service", service.as_str());
+
+        let login_page = session
+            .execute(session.client.get(session.route(login_url.clone())?))
+            .await?;
+        check_status(&login_page)?;
+        let form = parse_cas_form(&login_page.text().await?)?;
+
+        let passwd = match &form.salt {
+            Some(salt) => encrypt_password(session.passwd()?, salt)?,
+            None => session.passwd()?.to_string(),
+        };
+        let captcha = if form.captcha {
+            Some(
+                session
+                    .solve_captcha(session.route(self.server.join("captcha.html")?)?)
+                    .await?,
+            )
+        } else {
+            None
+        };
+        let mut params: Vec<(&str, &str)> = form
+            .fields
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect();
+        params.push(("username", session.user.as_str()));
+        params.push(("password", passwd.as_str()));
+        if let Some(answer) = &captcha {
+            params.push(("captchaResponse", answer.as_str()));
+        }
+
+        let action = match &form.action {
+            Some(action) => login_url.join(action)?,
+            None => login_url.clone(),
+        };
+        let final_response = session
+            .post_with_auto_redirect(session.route(action)?, &params)
+            .await?;
+        // Still on the CAS login page if the login failed
+        if final_response.url().path() == session.route(login_url)?.path() {
+            let selector = "#msg, #showErrorTip, .auth_error";
+            return Err(login_error(final_response, selector, captcha.is_some()).await?);
+        }
+        if session.is_login_page(&final_response) {
+            return Err(ZfError::AuthenticationFailed(
+                "the CAS ticket is not accepted".to_string(),
+            ));
+        }
+        check_status(&final_response)
+    }
+}
+
+#[test]
+fn test_encrypt_password() {
+    let data = b"ABCDEFGHJKMNPQRSTWXYZabcdefhijkmnprstwxyz2345678ABCDEFGHJKMNPQRSpasswd";
+    let encrypted = encrypt_cbc(data, b"rjBFAaHsNkKAhpoi", b"abcdefhijkmnprst").unwrap();
+    assert_eq!(
+        encode(encrypted),
+        "6zZAwJn7hoOIrvGKyXRK+iQAOQK6uOH+un85JSmrsnkiNin5I/pA4qw8Yje3dDVmg2X1LGdJrOqaxhW4nYNQWSsl2n9x0ER93gob+VpqKjk="
+    );
+    // 64 random characters and 6 characters of password, padded to 80 bytes
+    assert_eq!(
+        base64::decode(encrypt_password("passwd", "rjBFAaHsNkKAhpoi").unwrap())
+            .unwrap()
+            .len(),
+        80
+    );
+    assert!(encrypt_password("passwd", "short").is_err());
+}
+
+#[tokio::test]
+async fn test_cas_login() {
+    use crate::client::User;
+    use crate::mock::{MockCas, MockServer};
+    use crate::parsers::{SchoolYear, Semester};
+    use crate::session::SessionBuilder;
+
+    let server = MockServer::start("2018000000", "passwd").await;
+    let cas = MockCas::start("2018000000", "passwd").await;
+    server.trust_cas(&cas);
+    let session = |passwd: &str, cas_login: CasLogin| {
+        SessionBuilder::new()
+            .user("2018000000")
+            .passwd(passwd)
+            .base_url(server.base_url())
+            .authenticator(cas_login)
+            .build()
+    };
+
+    // The default service is under the base URL.
+    let cas_login = CasLogin::new(&cas.base_url()).unwrap();
+    let client = session("passwd", cas_login).login().await.unwrap();
+    assert_eq!(client.get_profile().await.unwrap().name(), "王小明");
+    assert_eq!(server.login_count(), 1);
+
+    // Login through CAS again after the session expired.
+    server.expire_sessions();
+    let scores = client
+        .get_score_list(SchoolYear::AllYear, Semester::All)
+        .await
+        .unwrap();
+    assert_eq!(scores.len(), 2);
+    assert_eq!(server.login_count(), 2);
+
+    let cas_login = CasLogin::new(&cas.base_url())
+        .unwrap()
+        .service(server.cas_service());
+    match session("wrong", cas_login).login().await {
+        Err(ZfError::AuthenticationFailed(message)) => {
+            assert_eq!(message, crate::mock::CAS_WRONG_PASSWORD_MESSAGE)
+        }
+        r => panic!("Unexpected login result: {:?}", r.map(|_| ())),
+    }
+}
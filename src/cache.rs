@@ -0,0 +1,361 @@
+//! Local cache of fetched pages in SQLite, enabled by the `sqlite` feature.
+//!
+//! Profiles, timetables, scores, majors and classes are stored by server, user, school year and
+//! semester.
+//! The raw pages are stored rather than the parsed data, so that a fixed parser works on the
+//! cached pages too. Attach the cache with `SessionBuilder::cache`, then the `User` and
+//! `Environment` methods read from it while the data is fresh.
+
+use crate::client::{Environment, User, ZfClient};
+use crate::config::url;
+use crate::parsers::{SchoolYear, Semester};
+use crate::{Result, ZfError};
+use chrono::Local;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Kind of cached data
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DataKind {
+    Profile,
+    Timetable,
+    Scores,
+    Majors,
+    Classes,
+}
+
+impl DataKind {
+    fn all() -> [DataKind; 5] {
+        [
+            DataKind::Profile,
+            DataKind::Timetable,
+            DataKind::Scores,
+            DataKind::Majors,
+            DataKind::Classes,
+        ]
+    }
+
+    fn path(self) -> &'static str {
+        match self {
+            DataKind::Profile => url::PROFILE,
+            DataKind::Timetable => url::TIME_TABLE,
+            DataKind::Scores => url::SCORE_LIST,
+            DataKind::Majors => url::MAJOR_LIST,
+            DataKind::Classes => url::CLASS_LIST,
+        }
+    }
+
+    fn from_path(path: &str) -> Option<DataKind> {
+        DataKind::all()
+            .iter()
+            .copied()
+            .find(|kind| kind.path() == path)
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            DataKind::Profile => "profile",
+            DataKind::Timetable => "timetable",
+            DataKind::Scores => "scores",
+            DataKind::Majors => "majors",
+            DataKind::Classes => "classes",
+        }
+    }
+
+    fn default_ttl(self) -> Duration {
+        const DAY: u64 = 24 * 60 * 60;
+        match self {
+            DataKind::Profile => Duration::from_secs(7 * DAY),
+            DataKind::Timetable => Duration::from_secs(DAY),
+            DataKind::Scores => Duration::from_secs(60 * 60),
+            DataKind::Majors | DataKind::Classes => Duration::from_secs(30 * DAY),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    /// Read fresh data, and store fetched data
+    Normal,
+    /// Read any cached data without network, even if it is stale
+    Offline,
+    /// Store fetched data without reading, used by `sync`
+    Refresh,
+}
+
+/// Version of the table layout, kept in `PRAGMA user_version`
+const SCHEMA_VERSION: i64 = 1;
+
+/// SQLite cache, which is cheap to clone and shared by the clones.
+#[derive(Debug, Clone)]
+pub struct Cache {
+    connection: Arc<Mutex<Connection>>,
+    ttl: HashMap<DataKind, Duration>,
+    mode: Mode,
+}
+
+/// School year and semester in the request form, which are part of the key.
+fn term_of(data: &[(&str, String)]) -> (String, String) {
+    let field = |names: &[&str]| {
+        data.iter()
+            .find(|(key, _)| names.contains(key))
+            .map(|(_, value)| value.clone())
+            .unwrap_or_default()
+    };
+    (field(&["xnm", "njdm_id"]), field(&["xqm"]))
+}
+
+impl Cache {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> Result<Self> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(connection: Connection) -> Result<Self> {
+        // Caches of older versions are not keyed by server, and they are simply dropped.
+        let version: i64 = connection.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        if version < SCHEMA_VERSION {
+            connection.execute("DROP TABLE IF EXISTS cache", [])?;
+            connection.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+        }
+        connection.execute(
+            "CREATE TABLE IF NOT EXISTS cache (
+                base_url    TEXT NOT NULL,
+                user        TEXT NOT NULL,
+                kind        TEXT NOT NULL,
+                school_year TEXT NOT NULL,
+                semester    TEXT NOT NULL,
+                page        TEXT NOT NULL,
+                fetched_at  INTEGER NOT NULL,
+                PRIMARY KEY (base_url, user, kind, school_year, semester)
+            )",
+            [],
+        )?;
+        Ok(Cache {
+            connection: Arc::new(Mutex::new(connection)),
+            ttl: DataKind::all()
+                .iter()
+                .copied()
+                .map(|kind| (kind, kind.default_ttl()))
+                .collect(),
+            mode: Mode::Normal,
+        })
+    }
+
+    /// How long the data is fresh. Defaults to 1 hour for scores, 1 day for timetables, 7 days
+    /// for profiles and 30 days for majors and classes.
+    pub fn ttl(mut self, kind: DataKind, ttl: Duration) -> Self {
+        self.ttl.insert(kind, ttl);
+        self
+    }
+
+    /// Read cached data only, even if it is stale. Requests which are not cached fail.
+    pub fn offline(mut self, offline: bool) -> Self {
+        self.mode = if offline { Mode::Offline } else { Mode::Normal };
+        self
+    }
+
+    fn connection(&self) -> std::sync::MutexGuard<'_, Connection> {
+        // A panic while holding the lock does not break the database.
+        self.connection
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Cached page of the request, `None` if it should be fetched from the server. The same
+    /// student number on another server has its own cache.
+    pub(crate) fn lookup(
+        &self,
+        user: &str,
+        base_url: &str,
+        path: &str,
+        data: &[(&str, String)],
+    ) -> Result<Option<String>> {
+        let kind = match (DataKind::from_path(path), self.mode) {
+            (_, Mode::Refresh) => return Ok(None),
+            (Some(kind), _) => kind,
+            (None, Mode::Offline) => return Err(not_cached(path)),
+            (None, Mode::Normal) => return Ok(None),
+        };
+        let (school_year, semester) = term_of(data);
+        let row: Option<(String, i64)> = self
+            .connection()
+            .query_row(
+                "SELECT page, fetched_at FROM cache
+                 WHERE base_url = ?1 AND user = ?2 AND kind = ?3 AND school_year = ?4
+                 AND semester = ?5",
+                params![base_url, user, kind.name(), school_year, semester],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+
+        match row {
+            Some((page, _)) if self.mode == Mode::Offline => Ok(Some(page)),
+            Some((page, fetched_at)) => {
+                let age = Local::now().timestamp() - fetched_at;
+                let fresh = (age as u64) < self.ttl[&kind].as_secs();
+                Ok(fresh.then_some(page))
+            }
+            None if self.mode == Mode::Offline => Err(not_cached(path)),
+            None => Ok(None),
+        }
+    }
+
+    pub(crate) fn store(
+        &self,
+        user: &str,
+        base_url: &str,
+        path: &str,
+        data: &[(&str, String)],
+        page: &str,
+    ) -> Result<()> {
+        let kind = match DataKind::from_path(path) {
+            Some(kind) => kind,
+            None => return Ok(()),
+        };
+        let (school_year, semester) = term_of(data);
+        self.connection().execute(
+            "INSERT OR REPLACE INTO cache
+             (base_url, user, kind, school_year, semester, page, fetched_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                base_url,
+                user,
+                kind.name(),
+                school_year,
+                semester,
+                page,
+                Local::now().timestamp()
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Remove all the cached data of the user, on all the servers.
+    pub fn clear(&self, user: &str) -> Result<()> {
+        self.connection()
+            .execute("DELETE FROM cache WHERE user = ?1", params![user])?;
+        Ok(())
+    }
+}
+
+fn not_cached(path: &str) -> ZfError {
+    ZfError::NotCached(path.to_string())
+}
+
+impl ZfClient {
+    /// Fetch the profile, and the timetable, scores and classes of the term from the server, and
+    /// refresh the cache. Majors of the entrance year in the profile are refreshed too.
+    pub async fn sync(&self, school_year: SchoolYear, semester: Semester) -> Result<()> {
+        let cache = match &self.session.cache {
+            Some(cache) => cache.clone(),
+            None => return Ok(()),
+        };
+        // The clone shares cookies with this client, only the cache mode differs.
+        let mut client = self.clone();
+        client.session.cache = Some(Cache {
+            mode: Mode::Refresh,
+            ..cache
+        });
+
+        let profile = client.get_profile().await?;
+        client
+            .get_timetable(school_year.clone(), semester.clone())
+            .await?;
+        client
+            .get_score_list(school_year.clone(), semester.clone())
+            .await?;
+        client.get_class_list(school_year, semester).await?;
+        // Enrollment date is like "2018-09-01".
+        if let Some(Ok(year)) = profile.enrollment_date().get(..4).map(str::parse) {
+            client.get_major_list(SchoolYear::SomeYear(year)).await?;
+        }
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_sqlite_cache() {
+    use crate::mock::MockServer;
+    use crate::session::SessionBuilder;
+
+    let server = MockServer::start("2018000000", "passwd").await;
+    let cache = Cache::open_in_memory().unwrap();
+    let mut client = SessionBuilder::new()
+        .user("2018000000")
+        .passwd("passwd")
+        .base_url(server.base_url())
+        .cache(cache.clone())
+        .build()
+        .login()
+        .await
+        .unwrap();
+    client
+        .sync(SchoolYear::SomeYear(2020), Semester::FirstTerm)
+        .await
+        .unwrap();
+
+    // Fresh data is read from the cache.
+    server.set_page(url::SCORE_LIST, r#"{"items": []}"#);
+    let scores = client
+        .get_score_list(SchoolYear::SomeYear(2020), Semester::FirstTerm)
+        .await
+        .unwrap();
+    assert_eq!(scores.len(), 2);
+    let majors = client
+        .get_major_list(SchoolYear::SomeYear(2018))
+        .await
+        .unwrap();
+    assert_eq!(majors.len(), 1);
+
+    // Stale data is fetched again.
+    client.session.cache = Some(cache.clone().ttl(DataKind::Scores, Duration::ZERO));
+    let scores = client
+        .get_score_list(SchoolYear::SomeYear(2020), Semester::FirstTerm)
+        .await
+        .unwrap();
+    assert!(scores.is_empty());
+
+    // Offline mode works without the server.
+    let base_url = server.base_url();
+    drop(server);
+    let offline = |base_url: &str| {
+        SessionBuilder::new()
+            .user("2018000000")
+            .passwd("passwd")
+            .base_url(base_url)
+            .cache(cache.clone().offline(true))
+            .build()
+            .restore()
+    };
+    // The same student number on another server has nothing cached.
+    assert!(matches!(
+        offline("http://127.0.0.1:1/jwglxt/").get_profile().await,
+        Err(ZfError::NotCached(_))
+    ));
+    let client = offline(&base_url);
+    assert!(client.get_profile().await.is_ok());
+    let timetable = client
+        .get_timetable(SchoolYear::SomeYear(2020), Semester::FirstTerm)
+        .await
+        .unwrap();
+    assert_eq!(timetable.len(), 2);
+    assert!(matches!(
+        client
+            .get_timetable(SchoolYear::SomeYear(2021), Semester::FirstTerm)
+            .await,
+        Err(ZfError::NotCached(_))
+    ));
+    assert!(matches!(
+        client
+            .get_exam_list(SchoolYear::SomeYear(2020), Semester::FirstTerm)
+            .await,
+        Err(ZfError::NotCached(_))
+    ));
+}
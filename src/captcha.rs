@@ -0,0 +1,111 @@
+//! Login with captcha.
+//!
+//! Some deployments, and SIT after several failed attempts, show a captcha (验证码) on the login
+//! page. Set a `CaptchaSolver` on `SessionBuilder::captcha_solver`, then the session fetches the
+//! captcha image and sends the answer of the solver on login.
+
+use crate::{Result, ZfError};
+use async_trait::async_trait;
+use scraper::{Html, Selector};
+use std::fmt;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Read the text in a captcha image, like prompting the user or calling an OCR service.
+#[async_trait]
+pub trait CaptchaSolver: Send + Sync {
+    /// `image` is the image file returned by the server, usually a JPEG.
+    async fn solve(&self, image: &[u8]) -> Result<String>;
+}
+
+/// Save the captcha image to a file, and read the answer from stdin.
+pub struct StdinSolver {
+    image_path: PathBuf,
+}
+
+impl StdinSolver {
+    pub fn new<P: AsRef<Path>>(image_path: P) -> Self {
+        StdinSolver {
+            image_path: image_path.as_ref().to_path_buf(),
+        }
+    }
+}
+
+#[async_trait]
+impl CaptchaSolver for StdinSolver {
+    async fn solve(&self, image: &[u8]) -> Result<String> {
+        std::fs::write(&self.image_path, image)?;
+        eprint!(
+            "Captcha is saved to {}, please input it: ",
+            self.image_path.display()
+        );
+        std::io::stderr().flush()?;
+
+        let answer = tokio::task::spawn_blocking(|| {
+            let mut line = String::new();
+            std::io::stdin().read_line(&mut line).map(|_| line)
+        })
+        .await
+        .map_err(|e| ZfError::Captcha(e.to_string()))??;
+        Ok(answer.trim().to_string())
+    }
+}
+
+/// Solver shared by the clones of a session.
+#[derive(Clone)]
+pub(crate) struct SharedSolver(pub(crate) Arc<dyn CaptchaSolver>);
+
+impl fmt::Debug for SharedSolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("CaptchaSolver")
+    }
+}
+
+/// Whether the login page has the captcha input.
+pub(crate) fn is_required(login_page: &str) -> bool {
+    let document = Html::parse_document(login_page);
+    let selector = Selector::parse("input#yzm").expect("Invalid captcha selector");
+    document.select(&selector).next().is_some()
+}
+
+#[tokio::test]
+async fn test_captcha_login() {
+    use crate::mock::MockServer;
+    use crate::session::SessionBuilder;
+
+    struct FixedSolver(&'static str);
+
+    #[async_trait]
+    impl CaptchaSolver for FixedSolver {
+        async fn solve(&self, image: &[u8]) -> Result<String> {
+            assert!(!image.is_empty());
+            Ok(self.0.to_string())
+        }
+    }
+
+    let server = MockServer::start("2018000000", "passwd").await;
+    server.require_captcha("x7k2");
+    let builder = || {
+        SessionBuilder::new()
+            .user("2018000000")
+            .passwd("passwd")
+            .base_url(server.base_url())
+    };
+
+    let result = builder().build().login().await;
+    assert!(matches!(result, Err(ZfError::CaptchaRequired)));
+    let result = builder()
+        .captcha_solver(FixedSolver("abcd"))
+        .build()
+        .login()
+        .await;
+    assert!(matches!(result, Err(ZfError::CaptchaRejected(_))));
+    let client = builder()
+        .captcha_solver(FixedSolver("x7k2"))
+        .build()
+        .login()
+        .await
+        .unwrap();
+    assert!(client.is_valid().await.unwrap());
+}
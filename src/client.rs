@@ -1,48 +1,238 @@
+mod course_selection;
 mod environment;
 mod user;
 
-use crate::config::USERAGENT;
-use crate::session::Session;
+use crate::config::url::INDEX;
+use crate::error::ZfError;
+use crate::session::{SavedSession, Session};
 use crate::Result;
+pub use course_selection::CourseSelection;
 pub use environment::Environment;
-use reqwest::header::{COOKIE, USER_AGENT};
-use reqwest::Response;
+use reqwest::{Method, Response};
 pub use user::User;
 
-#[derive(Debug)]
+/// Client of a logged-in account. Methods take `&self` and it is `Send + Sync`, so requests can
+/// run concurrently, like `tokio::join!` on several queries. Clones share the session.
+#[derive(Debug, Clone)]
 pub struct ZfClient {
     pub(crate) user: String,
     pub(crate) session: Session,
 }
 
 impl ZfClient {
-    async fn get_url(&mut self, url: &str, data: &[(&str, String)]) -> Result<Response> {
-        let response = self
-            .session
-            .client
-            .get(url)
-            .form(data)
-            .header(USER_AGENT, USERAGENT)
-            .header(COOKIE, self.session.get_cookie_string("jwxt.sit.edu.cn"))
-            .send()
-            .await?;
-        self.session
-            .sync_cookies("jwxt.sit.edu.cn", response.cookies());
+    /// Student number of the logged-in account
+    pub fn user(&self) -> &str {
+        &self.user
+    }
+
+    /// Export the session, see `SessionBuilder::from_saved` to restore it.
+    pub fn save(&self) -> SavedSession {
+        self.session.save()
+    }
+
+    /// Check whether the session is still logged in, without login again.
+    pub async fn is_valid(&self) -> Result<bool> {
+        let response = self.send(&Method::GET, INDEX, &[], true).await?;
+        Ok(response.status().is_success() && !self.session.is_login_page(&response))
+    }
+
+    /// Send the request, `query` means it has no side effect and can be retried.
+    async fn send(
+        &self,
+        method: &Method,
+        path: &str,
+        data: &[(&str, String)],
+        query: bool,
+    ) -> Result<Response> {
+        let url = self.session.url(path)?;
+        let request = self.session.client.request(method.clone(), url).form(data);
+        if query {
+            self.session.execute_query(request).await
+        } else {
+            self.session.execute(request).await
+        }
+    }
+
+    /// Send the request, and if the server says the session has expired, login again and replay
+    /// it once. If it fails to login again, the error of the login, like a changed password, is
+    /// the source of `ZfError::SessionExpired`.
+    async fn request(
+        &self,
+        method: Method,
+        path: &str,
+        data: &[(&str, String)],
+        query: bool,
+    ) -> Result<Response> {
+        let generation = self.session.generation();
+        let mut response = self.send(&method, path, data, query).await?;
+        if self.session.is_login_page(&response) {
+            self.session
+                .relogin(generation)
+                .await
+                .map_err(|e| match e {
+                    ZfError::SessionExpired(_) => e,
+                    e => ZfError::SessionExpired(Some(Box::new(e))),
+                })?;
+            response = self.send(&method, path, data, query).await?;
+            if self.session.is_login_page(&response) {
+                return Err(ZfError::SessionExpired(None));
+            }
+        }
+
+        let status = response.status();
+        if !status.is_success() && !status.is_redirection() {
+            return Err(ZfError::HttpStatus {
+                status,
+                url: response.url().to_string(),
+            });
+        }
         Ok(response)
     }
 
-    async fn post_url(&mut self, url: &str, data: &[(&str, String)]) -> Result<Response> {
-        let response = self
-            .session
-            .client
-            .post(url)
-            .form(data)
-            .header(USER_AGENT, USERAGENT)
-            .header(COOKIE, self.session.get_cookie_string("jwxt.sit.edu.cn"))
-            .send()
+    /// Request the page and parse it, parse errors are tagged with the path.
+    async fn fetch<T>(
+        &self,
+        method: Method,
+        path: &str,
+        data: &[(&str, String)],
+        query: bool,
+        parser: fn(&str) -> Result<T>,
+    ) -> Result<T> {
+        if let Some(replay) = &self.session.replay {
+            return parser(replay.find(&method, path, data)?).map_err(|e| e.at(path));
+        }
+        #[cfg(feature = "sqlite")]
+        if let Some(cache) = &self.session.cache {
+            if let Some(text) =
+                cache.lookup(&self.user, self.session.base_url.as_str(), path, data)?
+            {
+                return parser(&text).map_err(|e| e.at(path));
+            }
+        }
+        let text = self
+            .request(method.clone(), path, data, query)
+            .await?
+            .text()
             .await?;
-        self.session
-            .sync_cookies("jwxt.sit.edu.cn", response.cookies());
-        Ok(response)
+        if let Some(recorder) = &self.session.recorder {
+            recorder.record(&method, path, data, &text)?;
+        }
+        let result = parser(&text).map_err(|e| e.at(path))?;
+        // Only pages which can be parsed are cached.
+        #[cfg(feature = "sqlite")]
+        if let Some(cache) = &self.session.cache {
+            cache.store(
+                &self.user,
+                self.session.base_url.as_str(),
+                path,
+                data,
+                &text,
+            )?;
+        }
+        Ok(result)
+    }
+
+    async fn get_page<T>(
+        &self,
+        path: &str,
+        data: &[(&str, String)],
+        parser: fn(&str) -> Result<T>,
+    ) -> Result<T> {
+        self.fetch(Method::GET, path, data, true, parser).await
+    }
+
+    /// Post a query form, which is safe to retry.
+    async fn post_page<T>(
+        &self,
+        path: &str,
+        data: &[(&str, String)],
+        parser: fn(&str) -> Result<T>,
+    ) -> Result<T> {
+        self.fetch(Method::POST, path, data, true, parser).await
+    }
+
+    /// Post a form which changes something on the server, like selecting a class. It is never
+    /// retried on errors, so that it is not submitted twice.
+    async fn submit_page<T>(
+        &self,
+        path: &str,
+        data: &[(&str, String)],
+        parser: fn(&str) -> Result<T>,
+    ) -> Result<T> {
+        self.fetch(Method::POST, path, data, false, parser).await
+    }
+}
+
+#[tokio::test]
+async fn test_concurrent_requests() {
+    use crate::mock::MockServer;
+    use crate::parsers::{SchoolYear, Semester};
+    use crate::session::SessionBuilder;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<ZfClient>();
+
+    let server = MockServer::start("2018000000", "passwd").await;
+    let client = SessionBuilder::new()
+        .user("2018000000")
+        .passwd("passwd")
+        .base_url(server.base_url())
+        .build()
+        .login()
+        .await
+        .unwrap();
+
+    // All the requests find the session expired, but only one of them logs in again.
+    server.expire_sessions();
+    let (profile, scores, timetable) = tokio::join!(
+        client.get_profile(),
+        client.get_score_list(SchoolYear::AllYear, Semester::All),
+        client.get_timetable(SchoolYear::SomeYear(2020), Semester::FirstTerm),
+    );
+    assert_eq!(profile.unwrap().name(), "王小明");
+    assert_eq!(scores.unwrap().len(), 2);
+    assert_eq!(timetable.unwrap().len(), 2);
+    assert_eq!(server.login_count(), 2);
+
+    // Clones share the cookies.
+    let other = client.clone();
+    tokio::spawn(async move { other.get_profile().await.map(|_| ()) })
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(server.login_count(), 2);
+}
+
+#[tokio::test]
+async fn test_relogin() {
+    use crate::mock::{MockServer, WRONG_PASSWORD_MESSAGE};
+    use crate::session::SessionBuilder;
+
+    let server = MockServer::start("2018000000", "passwd").await;
+    let client = SessionBuilder::new()
+        .user("2018000000")
+        .passwd("passwd")
+        .base_url(server.base_url())
+        .build()
+        .login()
+        .await
+        .unwrap();
+
+    // The request is replayed after login again.
+    server.expire_sessions();
+    assert_eq!(client.get_profile().await.unwrap().name(), "王小明");
+    assert_eq!(server.login_count(), 2);
+    assert_eq!(server.login_attempts(), 2);
+
+    // The session is expired if it fails to login again, and the cause is kept.
+    server.set_passwd("changed");
+    server.expire_sessions();
+    match client.get_profile().await {
+        Err(ZfError::SessionExpired(Some(e))) => match *e {
+            ZfError::AuthenticationFailed(message) => assert_eq!(message, WRONG_PASSWORD_MESSAGE),
+            e => panic!("Unexpected login error: {}", e),
+        },
+        r => panic!("Unexpected result: {:?}", r.map(|_| ())),
     }
+    assert_eq!(server.login_attempts(), 3);
 }
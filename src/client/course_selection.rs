@@ -0,0 +1,149 @@
+use crate::client::ZfClient;
+use crate::config::url::{
+    SELECTION_CLASS_LIST, SELECTION_COURSE_LIST, SELECTION_DROP, SELECTION_INDEX, SELECTION_SELECT,
+    SELECTION_SELECTED,
+};
+use crate::parsers::*;
+use crate::Result;
+use async_trait::async_trait;
+use std::collections::HashMap;
+
+#[async_trait]
+pub trait CourseSelection {
+    /// Load parameters and course types of the current selection round.
+    async fn get_selection_round(&self) -> Result<SelectionRound>;
+
+    async fn get_available_course_list(
+        &self,
+        round: &SelectionRound,
+        course_type: &CourseType,
+    ) -> Result<Vec<SelectCourse>>;
+
+    async fn get_teaching_class_list(
+        &self,
+        round: &SelectionRound,
+        course_type: &CourseType,
+        course: &SelectCourse,
+    ) -> Result<Vec<TeachingClass>>;
+
+    async fn select_class(
+        &self,
+        round: &SelectionRound,
+        course_type: &CourseType,
+        course: &SelectCourse,
+        class: &TeachingClass,
+    ) -> Result<SelectionResult>;
+
+    async fn drop_class(
+        &self,
+        round: &SelectionRound,
+        class: &SelectedClass,
+    ) -> Result<SelectionResult>;
+
+    async fn get_selected_class_list(
+        &self,
+        round: &SelectionRound,
+        course_type: &CourseType,
+    ) -> Result<Vec<SelectedClass>>;
+}
+
+/// Hidden parameters of the round, with the course type and `extra` overriding them.
+fn selection_form<'a>(
+    round: &'a SelectionRound,
+    course_type: Option<&CourseType>,
+    extra: Vec<(&'a str, String)>,
+) -> Vec<(&'a str, String)> {
+    let mut data: HashMap<&str, String> = round
+        .params
+        .iter()
+        .map(|(k, v)| (k.as_str(), v.clone()))
+        .collect();
+    if let Some(course_type) = course_type {
+        data.insert("kklxdm", course_type.type_id.clone());
+        data.insert("xkkz_id", course_type.round_id.clone());
+    }
+    data.extend(extra);
+    data.into_iter().collect()
+}
+
+#[async_trait]
+impl CourseSelection for ZfClient {
+    async fn get_selection_round(&self) -> Result<SelectionRound> {
+        self.get_page(SELECTION_INDEX, &[], parse_selection_round_page)
+            .await
+    }
+
+    async fn get_available_course_list(
+        &self,
+        round: &SelectionRound,
+        course_type: &CourseType,
+    ) -> Result<Vec<SelectCourse>> {
+        let extra = vec![
+            ("rwlx", "2".to_string()),
+            ("xkly", "0".to_string()),
+            ("kspage", "1".to_string()),
+            ("jspage", "10000".to_string()),
+        ];
+        let data = selection_form(round, Some(course_type), extra);
+        self.post_page(SELECTION_COURSE_LIST, &data, parse_available_course_page)
+            .await
+    }
+
+    async fn get_teaching_class_list(
+        &self,
+        round: &SelectionRound,
+        course_type: &CourseType,
+        course: &SelectCourse,
+    ) -> Result<Vec<TeachingClass>> {
+        let extra = vec![
+            ("rwlx", "2".to_string()),
+            ("xkly", "0".to_string()),
+            ("kch_id", course.inner_course_id.clone()),
+        ];
+        let data = selection_form(round, Some(course_type), extra);
+        self.post_page(SELECTION_CLASS_LIST, &data, parse_teaching_class_page)
+            .await
+    }
+
+    async fn select_class(
+        &self,
+        round: &SelectionRound,
+        course_type: &CourseType,
+        course: &SelectCourse,
+        class: &TeachingClass,
+    ) -> Result<SelectionResult> {
+        let extra = vec![
+            ("jxb_ids", class.select_id.clone()),
+            ("kch_id", course.inner_course_id.clone()),
+            ("qz", "0".to_string()),
+        ];
+        let data = selection_form(round, Some(course_type), extra);
+        self.submit_page(SELECTION_SELECT, &data, parse_select_result)
+            .await
+    }
+
+    async fn drop_class(
+        &self,
+        round: &SelectionRound,
+        class: &SelectedClass,
+    ) -> Result<SelectionResult> {
+        let extra = vec![
+            ("jxb_ids", class.select_id.clone()),
+            ("kch_id", class.inner_course_id.clone()),
+            ("txbsfrl", "0".to_string()),
+        ];
+        let data = selection_form(round, None, extra);
+        self.submit_page(SELECTION_DROP, &data, parse_drop_result)
+            .await
+    }
+
+    async fn get_selected_class_list(
+        &self,
+        round: &SelectionRound,
+        course_type: &CourseType,
+    ) -> Result<Vec<SelectedClass>> {
+        let data = selection_form(round, Some(course_type), vec![]);
+        self.post_page(SELECTION_SELECTED, &data, parse_selected_class_page)
+            .await
+    }
+}
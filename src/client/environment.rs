@@ -6,16 +6,16 @@ use async_trait::async_trait;
 
 #[async_trait]
 pub trait Environment {
-    async fn get_major_list(&mut self, entrance_year: SchoolYear) -> Result<Vec<Major>>;
+    async fn get_major_list(&self, entrance_year: SchoolYear) -> Result<Vec<Major>>;
 
     async fn get_class_list(
-        &mut self,
+        &self,
         school_year: SchoolYear,
         semester: Semester,
     ) -> Result<Vec<Class>>;
 
     async fn get_suggested_course_list(
-        &mut self,
+        &self,
         school_year: SchoolYear,
         semester: Semester,
         major_id: &str,
@@ -26,15 +26,14 @@ pub trait Environment {
 
 #[async_trait]
 impl Environment for ZfClient {
-    async fn get_major_list(&mut self, entrance_year: SchoolYear) -> Result<Vec<Major>> {
+    async fn get_major_list(&self, entrance_year: SchoolYear) -> Result<Vec<Major>> {
         let param = [("njdm_id", entrance_year.to_string())];
-        let page = self.get_url(MAJOR_LIST, &param).await?;
-        let text = page.text().await?;
-        parse_major_list_page(&text)
+        self.get_page(MAJOR_LIST, &param, parse_major_list_page)
+            .await
     }
 
     async fn get_class_list(
-        &mut self,
+        &self,
         school_year: SchoolYear,
         semester: Semester,
     ) -> Result<Vec<Class>> {
@@ -43,13 +42,12 @@ impl Environment for ZfClient {
             ("xqm", semester.to_raw().to_string()),
             ("queryModel.showCount", 10000.to_string()),
         ];
-        let page = self.post_url(CLASS_LIST, &data).await?;
-        let text = page.text().await?;
-        parse_class_list_page(&text)
+        self.post_page(CLASS_LIST, &data, parse_class_list_page)
+            .await
     }
 
     async fn get_suggested_course_list(
-        &mut self,
+        &self,
         school_year: SchoolYear,
         semester: Semester,
         major_id: &str,
@@ -83,8 +81,7 @@ impl Environment for ZfClient {
             ("tjkbzdm", "1".to_string()),
             ("tjkbzxsdm", "0".to_string()),
         ];
-        let page = self.post_url(SUGGESTED_COURSE, &data).await?;
-        let text = page.text().await?;
-        parse_timetable_page(&text)
+        self.post_page(SUGGESTED_COURSE, &data, parse_timetable_page)
+            .await
     }
 }
@@ -1,18 +1,17 @@
 use crate::client::ZfClient;
-use crate::config::url::{PROFILE, SCORE_LIST, TIME_TABLE};
-use crate::config::USERAGENT;
+use crate::config::url::{EXAM_LIST, PROFILE, SCORE_DETAIL, SCORE_LIST, TIME_TABLE};
+use crate::gpa::{GpaCalculator, GpaReport};
 use crate::parsers::*;
 use crate::Result;
 use async_trait::async_trait;
-use reqwest::header::{COOKIE, USER_AGENT};
 use std::collections::HashMap;
 
 #[async_trait]
 pub trait User {
-    async fn get_profile(&mut self) -> Result<Profile>;
+    async fn get_profile(&self) -> Result<Profile>;
 
     async fn get_timetable(
-        &mut self,
+        &self,
         school_year: SchoolYear,
         semester: Semester,
     ) -> Result<Vec<Course>>;
@@ -20,40 +19,43 @@ pub trait User {
     fn group_timetable(course_list: Vec<Course>) -> HashMap<String, Vec<Course>>;
 
     async fn get_group_timetable(
-        &mut self,
+        &self,
         school_year: SchoolYear,
         semester: Semester,
     ) -> Result<HashMap<String, Vec<Course>>>;
 
     async fn get_score_list(
-        &mut self,
+        &self,
         school_year: SchoolYear,
         semester: Semester,
     ) -> Result<Vec<Score>>;
 
+    /// Get component scores (平时/期中/期末) of a score in the score list.
+    async fn get_score_detail(&self, score: &Score) -> Result<Vec<ScoreComponent>>;
+
     fn calculate_gpa(score_list: Vec<Score>) -> Result<f32>;
 
-    async fn get_gpa(&mut self, school_year: SchoolYear, semester: Semester) -> Result<f32>;
+    async fn get_gpa(&self, school_year: SchoolYear, semester: Semester) -> Result<f32>;
+
+    async fn get_gpa_report(
+        &self,
+        school_year: SchoolYear,
+        semester: Semester,
+        calculator: &GpaCalculator,
+    ) -> Result<GpaReport>;
+
+    async fn get_exam_list(&self, school_year: SchoolYear, semester: Semester)
+        -> Result<Vec<Exam>>;
 }
 
 #[async_trait]
 impl User for ZfClient {
-    async fn get_profile(&mut self) -> Result<Profile> {
-        let page = self
-            .session
-            .client
-            .get(PROFILE)
-            .header(USER_AGENT, USERAGENT)
-            .header(COOKIE, self.session.get_cookie_string("jwxt.sit.edu.cn"))
-            .send()
-            .await?;
-        self.session.sync_cookies("jwxt.sit.edu.cn", page.cookies());
-        let text = page.text().await?;
-        parse_profile_page(&text)
+    async fn get_profile(&self) -> Result<Profile> {
+        self.get_page(PROFILE, &[], parse_profile_page).await
     }
 
     async fn get_timetable(
-        &mut self,
+        &self,
         school_year: SchoolYear,
         semester: Semester,
     ) -> Result<Vec<Course>> {
@@ -61,9 +63,8 @@ impl User for ZfClient {
             ("xnm", school_year.to_string()),
             ("xqm", semester.to_raw().to_string()),
         ];
-        let page = self.post_url(TIME_TABLE, &data).await?;
-        let text = page.text().await?;
-        parse_timetable_page(&text)
+        self.post_page(TIME_TABLE, &data, parse_timetable_page)
+            .await
     }
 
     fn group_timetable(course_list: Vec<Course>) -> HashMap<String, Vec<Course>> {
@@ -82,7 +83,7 @@ impl User for ZfClient {
     }
 
     async fn get_group_timetable(
-        &mut self,
+        &self,
         school_year: SchoolYear,
         semester: Semester,
     ) -> Result<HashMap<String, Vec<Course>>> {
@@ -91,7 +92,7 @@ impl User for ZfClient {
     }
 
     async fn get_score_list(
-        &mut self,
+        &self,
         school_year: SchoolYear,
         semester: Semester,
     ) -> Result<Vec<Score>> {
@@ -100,17 +101,52 @@ impl User for ZfClient {
             ("xqm", semester.to_raw().to_string()),
             ("queryModel.showCount", "5000".to_string()),
         ];
-        let page = self.post_url(SCORE_LIST, &data).await?;
-        let text = page.text().await?;
-        parse_score_list_page(&text)
+        self.post_page(SCORE_LIST, &data, parse_score_list_page)
+            .await
+    }
+
+    async fn get_score_detail(&self, score: &Score) -> Result<Vec<ScoreComponent>> {
+        // School year is like "2020-2021", while the server wants "2020".
+        let school_year = score.school_year.split('-').next().unwrap_or_default();
+        let data = [
+            ("jxb_id", score.class_id.clone()),
+            ("xnm", school_year.to_string()),
+            ("xqm", score.semester.to_raw().to_string()),
+            ("kcmc", score.course.clone()),
+        ];
+        self.post_page(SCORE_DETAIL, &data, parse_score_detail_page)
+            .await
     }
 
     fn calculate_gpa(score_list: Vec<Score>) -> Result<f32> {
         Ok(calculate_gpa(score_list))
     }
 
-    async fn get_gpa(&mut self, school_year: SchoolYear, semester: Semester) -> Result<f32> {
+    async fn get_gpa(&self, school_year: SchoolYear, semester: Semester) -> Result<f32> {
         let score_list = self.get_score_list(school_year, semester).await?;
         return ZfClient::calculate_gpa(score_list);
     }
+
+    async fn get_gpa_report(
+        &self,
+        school_year: SchoolYear,
+        semester: Semester,
+        calculator: &GpaCalculator,
+    ) -> Result<GpaReport> {
+        let score_list = self.get_score_list(school_year, semester).await?;
+        Ok(calculator.calculate(&score_list))
+    }
+
+    async fn get_exam_list(
+        &self,
+        school_year: SchoolYear,
+        semester: Semester,
+    ) -> Result<Vec<Exam>> {
+        let data = [
+            ("xnm", school_year.to_string()),
+            ("xqm", semester.to_raw().to_string()),
+            ("queryModel.showCount", "5000".to_string()),
+        ];
+        self.post_page(EXAM_LIST, &data, parse_exam_list_page).await
+    }
 }
@@ -1,39 +1,59 @@
+/// Default server address for 正方教务系统, including the `/jwglxt/` path prefix.
+pub const DEFAULT_BASE_URL: &str = "http://jwxt.sit.edu.cn/jwglxt/";
+
 /// URL that probably used in the program
+///
+/// All of them are relative to the base URL of the session, see `SessionBuilder::base_url`.
 pub(crate) mod url {
-    use const_format::concatcp;
-
-    /// Server address for 正方教务系统
-    pub const HOME: &str = "http://jwxt.sit.edu.cn";
-
     /* Login related */
 
-    pub const LOGIN: &str = concatcp!(HOME, "/jwglxt/xtgl/login_slogin.html");
-    pub const RSA_PUBLIC_KEY: &str = concatcp!(HOME, "/jwglxt/xtgl/login_getPublicKey.html");
+    pub const LOGIN: &str = "xtgl/login_slogin.html";
+    pub const RSA_PUBLIC_KEY: &str = "xtgl/login_getPublicKey.html";
+    /// Captcha image, shown on the login page after failed attempts
+    pub const CAPTCHA: &str = "kaptcha";
+    /// Entrance of the unified authentication on SIT, which accepts the ticket from the CAS server
+    pub const CAS_SERVICE: &str = "sso/jziotlogin";
 
     /* function related */
 
+    /// Index page after login, which is cheap to check whether the session is valid
+    pub const INDEX: &str = "xtgl/index_initMenu.html";
+
     /// Score list page
-    pub const SCORE_LIST: &str = concatcp!(
-        HOME,
-        "/jwglxt/cjcx/cjcx_cxDgXscj.html?doType=query&gnmkdm=N305005"
-    );
+    pub const SCORE_LIST: &str = "cjcx/cjcx_cxDgXscj.html?doType=query&gnmkdm=N305005";
+    /// Score detail of a teaching class
+    pub const SCORE_DETAIL: &str = "cjcx/cjcx_cxCjxqGjh.html?gnmkdm=N305005";
+    /// Exam list page
+    pub const EXAM_LIST: &str = "kwgl/kscx_cxXsksxxIndex.html?doType=query&gnmkdm=N358105";
     /// Time tanle page
-    pub const TIME_TABLE: &str = concatcp!(HOME, "/jwglxt/kbcx/xskbcx_cxXsKb.html?gnmkdm=N253508");
+    pub const TIME_TABLE: &str = "kbcx/xskbcx_cxXsKb.html?gnmkdm=N253508";
     /// Personal profile page
-    pub const PROFILE: &str = concatcp!(
-        HOME,
-        "/jwglxt/xsxxxggl/xsgrxxwh_cxXsgrxx.html?gnmkdm=N100801&layout=default"
-    );
+    pub const PROFILE: &str = "xsxxxggl/xsgrxxwh_cxXsgrxx.html?gnmkdm=N100801&layout=default";
     /// Major list page
-    pub const MAJOR_LIST: &str =
-        concatcp!(HOME, "/jwglxt/xtgl/comm_cxZyfxList.html?gnmkdm=N214505");
+    pub const MAJOR_LIST: &str = "xtgl/comm_cxZyfxList.html?gnmkdm=N214505";
     /// Class list page
-    pub const CLASS_LIST: &str =
-        concatcp!(HOME, "/jwglxt/xtgl/comm_cxBjdmList.html?gnmkdm=N214505");
+    pub const CLASS_LIST: &str = "xtgl/comm_cxBjdmList.html?gnmkdm=N214505";
     /// Suggested course and time table
-    pub const SUGGESTED_COURSE: &str =
-        concatcp!(HOME, "/jwglxt/kbdy/bjkbdy_cxBjKb.html?gnmkdm=N214505");
+    pub const SUGGESTED_COURSE: &str = "kbdy/bjkbdy_cxBjKb.html?gnmkdm=N214505";
+
+    /* Course selection related */
+
+    /// Course selection index, with the parameters of the current round
+    pub const SELECTION_INDEX: &str =
+        "xsxk/zzxkyzb_cxZzxkYzbIndex.html?gnmkdm=N253512&layout=default";
+    /// Available course list of a course type
+    pub const SELECTION_COURSE_LIST: &str = "xsxk/zzxkyzb_cxZzxkYzbPartDisplay.html?gnmkdm=N253512";
+    /// Teaching classes of a course
+    pub const SELECTION_CLASS_LIST: &str = "xsxk/zzxkyzbjk_cxJxbWithKchZzxkYzb.html?gnmkdm=N253512";
+    /// Select a teaching class
+    pub const SELECTION_SELECT: &str = "xsxk/zzxkyzbjk_xkBcZyZzxkYzb.html?gnmkdm=N253512";
+    /// Drop a teaching class
+    pub const SELECTION_DROP: &str = "xsxk/zzxkyzb_tuikBcZzxkYzb.html?gnmkdm=N253512";
+    /// Selected teaching classes
+    pub const SELECTION_SELECTED: &str = "xsxk/zzxkyzb_cxZzxkYzbChoosedDisplay.html?gnmkdm=N253512";
 }
 
-pub(crate) const USERAGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/87.0.4280.88 ' \
-'Safari/537.36 Edg/87.0.664.66";
+/// Default User-Agent of the requests, the same as a desktop browser.
+pub const DEFAULT_USER_AGENT: &str =
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 \
+    (KHTML, like Gecko) Chrome/87.0.4280.88 Safari/537.36 Edg/87.0.664.66";
@@ -1,9 +1,140 @@
-pub use anyhow::Result;
+pub type Result<T> = std::result::Result<T, ZfError>;
 
 #[derive(Debug, thiserror::Error)]
 pub enum ZfError {
-    #[error("Session error : {0}.")]
-    SessionError(String),
-    #[error("Can't get public key")]
-    PublicKeyError,
+    /// Wrong user or password, with the message shown on the login page
+    #[error("Authentication failed: {0}.")]
+    AuthenticationFailed(String),
+    /// Captcha is shown on the login page but no `CaptchaSolver` is set
+    #[error("Captcha is required to login.")]
+    CaptchaRequired,
+    /// The answer of the `CaptchaSolver` is wrong, with the message shown on the login page
+    #[error("Captcha is rejected: {0}.")]
+    CaptchaRejected(String),
+    /// The `CaptchaSolver` failed to give an answer
+    #[error("Captcha solver failed: {0}.")]
+    Captcha(String),
+    /// Session expired, and it can not login again or the server still rejects it after login.
+    /// The error of the failed login is kept as the source, `None` if it did not try to login,
+    /// like a restored session without password.
+    #[error("Session expired and failed to login again.")]
+    SessionExpired(#[source] Option<Box<ZfError>>),
+    #[error("Server returned {status} for {url}.")]
+    HttpStatus {
+        status: reqwest::StatusCode,
+        url: String,
+    },
+    #[error("Transport error: {0}")]
+    Transport(#[from] reqwest::Error),
+    /// The page is not as expected, usually because the server changed its layout.
+    #[error("Can't parse {field} of {}: {reason}.", endpoint_name(.endpoint))]
+    Parse {
+        /// Page path in `config::url`, empty if the content does not come from the server
+        endpoint: String,
+        field: String,
+        reason: String,
+    },
+    #[error("Invalid url: {0}.")]
+    InvalidUrl(#[from] url::ParseError),
+    #[error("Encryption error: {0}.")]
+    Encryption(String),
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    /// The request is not in the recording being replayed
+    #[error("No recording for {method} {path}.")]
+    NotRecorded { method: String, path: String },
+    /// The page is not in the offline cache
+    #[cfg(feature = "sqlite")]
+    #[error("{0} is not cached.")]
+    NotCached(String),
+    /// The student number is not in the `AccountPool`
+    #[error("No account {0} in the pool.")]
+    UnknownAccount(String),
+    /// A watcher sink failed to send the notification
+    #[error("Failed to notify: {0}.")]
+    Notify(String),
+    #[cfg(feature = "sqlite")]
+    #[error("Database error: {0}")]
+    Database(#[from] rusqlite::Error),
+}
+
+fn endpoint_name(endpoint: &str) -> &str {
+    if endpoint.is_empty() {
+        "input"
+    } else {
+        endpoint
+    }
+}
+
+impl ZfError {
+    /// Parse error on a field, the endpoint is filled by the client with `ZfError::at`.
+    pub(crate) fn parse<F: ToString, R: ToString>(field: F, reason: R) -> Self {
+        ZfError::Parse {
+            endpoint: String::new(),
+            field: field.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+
+    /// Whether logging in again does not help, like a wrong password or a captcha which can not be
+    /// solved. Retrying on these errors may lock the account.
+    pub(crate) fn is_login_rejected(&self) -> bool {
+        match self {
+            ZfError::AuthenticationFailed(_)
+            | ZfError::CaptchaRequired
+            | ZfError::CaptchaRejected(_)
+            | ZfError::Captcha(_)
+            | ZfError::SessionExpired(None) => true,
+            ZfError::SessionExpired(Some(e)) => e.is_login_rejected(),
+            _ => false,
+        }
+    }
+
+    /// Fill the endpoint of a parse error.
+    pub(crate) fn at(self, path: &str) -> Self {
+        match self {
+            ZfError::Parse {
+                endpoint,
+                field,
+                reason,
+            } if endpoint.is_empty() => ZfError::Parse {
+                endpoint: path.to_string(),
+                field,
+                reason,
+            },
+            e => e,
+        }
+    }
+}
+
+impl From<serde_json::Error> for ZfError {
+    fn from(e: serde_json::Error) -> Self {
+        ZfError::parse("json", e)
+    }
+}
+
+impl From<toml::de::Error> for ZfError {
+    fn from(e: toml::de::Error) -> Self {
+        ZfError::parse("toml", e)
+    }
+}
+
+#[test]
+fn test_parse_error_endpoint() {
+    let error =
+        crate::parsers::parse_exam_list_page(r#"{"items": [{"kssj": "2021-13-45(09:00-11:00)"}]}"#)
+            .unwrap_err()
+            .at("kwgl/kscx_cxXsksxxIndex.html");
+    match &error {
+        ZfError::Parse {
+            endpoint, field, ..
+        } => {
+            assert_eq!(endpoint, "kwgl/kscx_cxXsksxxIndex.html");
+            assert_eq!(field, "kssj");
+        }
+        e => panic!("Unexpected error: {}", e),
+    }
+    // An endpoint is only filled once.
+    let error = error.at("index.html");
+    assert!(error.to_string().contains("kwgl/kscx_cxXsksxxIndex.html"));
 }
@@ -0,0 +1,3 @@
+mod ics;
+
+pub use ics::IcsExporter;
@@ -0,0 +1,184 @@
+use crate::parsers::Course;
+use crate::schedule::PeriodSchedule;
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, Utc};
+
+/// Time zone of all the events, China has no daylight saving time.
+const TIME_ZONE: &str = "Asia/Shanghai";
+const PRODUCT_ID: &str = "-//SIT-Yiban//zf-tools-rs//CN";
+
+/// Export timetable to iCalendar (RFC 5545), one event for each week the course takes place.
+pub struct IcsExporter {
+    /// Monday of the first week in the term
+    term_start: NaiveDate,
+    /// Clock time of each period
+    schedule: PeriodSchedule,
+    /// Calendar name shown in calendar apps
+    name: String,
+}
+
+impl IcsExporter {
+    /// `term_start` is any day in the first week, `schedule` gives the clock time of periods.
+    pub fn new(term_start: NaiveDate, schedule: PeriodSchedule) -> Self {
+        let term_start =
+            term_start - Duration::days(term_start.weekday().num_days_from_monday() as i64);
+        IcsExporter {
+            term_start,
+            schedule,
+            name: String::from("课程表"),
+        }
+    }
+
+    pub fn name<T: ToString>(mut self, name: T) -> Self {
+        self.name = name.to_string();
+        self
+    }
+
+    pub fn export(&self, courses: &[Course]) -> String {
+        let stamp = format_utc(Utc::now().naive_utc());
+        let mut lines = vec![
+            "BEGIN:VCALENDAR".to_string(),
+            "VERSION:2.0".to_string(),
+            format!("PRODID:{}", PRODUCT_ID),
+            "CALSCALE:GREGORIAN".to_string(),
+            format!("X-WR-CALNAME:{}", escape_text(&self.name)),
+            format!("X-WR-TIMEZONE:{}", TIME_ZONE),
+            "BEGIN:VTIMEZONE".to_string(),
+            format!("TZID:{}", TIME_ZONE),
+            "BEGIN:STANDARD".to_string(),
+            "DTSTART:19700101T000000".to_string(),
+            "TZOFFSETFROM:+0800".to_string(),
+            "TZOFFSETTO:+0800".to_string(),
+            "TZNAME:CST".to_string(),
+            "END:STANDARD".to_string(),
+            "END:VTIMEZONE".to_string(),
+        ];
+        for course in courses {
+            lines.extend(self.course_events(course, &stamp));
+        }
+        lines.push("END:VCALENDAR".to_string());
+
+        lines
+            .iter()
+            .map(|line| fold_line(line))
+            .collect::<Vec<String>>()
+            .join("")
+    }
+
+    fn course_events(&self, course: &Course, stamp: &str) -> Vec<String> {
+        let (day, periods) = match (course.day, course.periods) {
+            (Some(day), Some(periods)) => (day.number() as i64, periods),
+            _ => return vec![],
+        };
+
+        let mut description = format!("教师: {}", course.teachers.join(", "));
+        description += &format!("\n教学班: {}", course.dyn_class_id);
+        let mut events = Vec::new();
+        for week in course.weeks.iter() {
+            let date = self.term_start + Duration::days((week as i64 - 1) * 7 + day - 1);
+            let (start_time, end_time) = match course.time_range(&self.schedule, date) {
+                Some(range) => range,
+                None => continue,
+            };
+            // Stable across exports, so that calendar apps update the event instead of adding one.
+            let uid = format!(
+                "{}-{}-{}-{}-{}@zf-tools-rs",
+                course.course_id, course.dyn_class_id, week, day, periods.start
+            );
+            events.extend(vec![
+                "BEGIN:VEVENT".to_string(),
+                format!("UID:{}", escape_text(&uid)),
+                format!("DTSTAMP:{}", stamp),
+                format!(
+                    "DTSTART;TZID={}:{}",
+                    TIME_ZONE,
+                    format_local(date.and_time(start_time))
+                ),
+                format!(
+                    "DTEND;TZID={}:{}",
+                    TIME_ZONE,
+                    format_local(date.and_time(end_time))
+                ),
+                format!("SUMMARY:{}", escape_text(&course.course_name)),
+                format!(
+                    "LOCATION:{}",
+                    escape_text(format!("{} {}", course.campus, course.place).trim())
+                ),
+                format!("DESCRIPTION:{}", escape_text(&description)),
+                "END:VEVENT".to_string(),
+            ]);
+        }
+        events
+    }
+}
+
+fn format_local(time: NaiveDateTime) -> String {
+    time.format("%Y%m%dT%H%M%S").to_string()
+}
+
+fn format_utc(time: NaiveDateTime) -> String {
+    time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// Escape TEXT value, see RFC 5545 3.3.11
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Fold content line longer than 75 octets, and end it with CRLF, see RFC 5545 3.1
+fn fold_line(line: &str) -> String {
+    let mut result = String::new();
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > 75 {
+            result.push_str("\r\n ");
+            length = 1;
+        }
+        result.push(c);
+        length += c.len_utf8();
+    }
+    result.push_str("\r\n");
+    result
+}
+
+#[test]
+fn test_export_ics() {
+    use crate::parsers::parse_timetable_page;
+
+    let page = r#"
+{
+    "kbList": [
+        {
+            "cdmc": "一教A204",
+            "jcs": "1-2",
+            "jxbmc": "(2020-2021-2)-B1020012-03",
+            "jxbzc": "19104211,19104212",
+            "kch": "B1020012",
+            "kcmc": "大学物理A2",
+            "xf": "4.0",
+            "xm": "张三",
+            "xqjmc": "星期二",
+            "xqmc": "奉贤校区",
+            "zcd": "1-3周(单),6周",
+            "zxs": "64"
+        }
+    ]
+}"#;
+    let courses = parse_timetable_page(page).unwrap();
+    // Wednesday in the first week
+    let exporter = IcsExporter::new(
+        NaiveDate::from_ymd_opt(2021, 3, 3).unwrap(),
+        PeriodSchedule::sit(),
+    );
+    let calendar = exporter.export(&courses);
+    println!("{}", calendar);
+
+    assert_eq!(calendar.matches("BEGIN:VEVENT").count(), 3);
+    assert!(calendar.contains("DTSTART;TZID=Asia/Shanghai:20210302T082000\r\n"));
+    assert!(calendar.contains("DTEND;TZID=Asia/Shanghai:20210316T095500\r\n"));
+    assert!(calendar.contains("DTSTART;TZID=Asia/Shanghai:20210406T082000\r\n"));
+    assert!(calendar.contains("UID:B1020012-(2020-2021-2)-B1020012-03-6-2-1@zf-tools-rs\r\n"));
+    assert!(calendar.lines().all(|line| line.len() <= 76));
+}
@@ -0,0 +1,288 @@
+use crate::parsers::{Grade, Score, Semester};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Rule to convert a score to grade point.
+pub trait GpaStrategy: Send + Sync {
+    /// Grade point of the score, `None` if the score is not counted, like pass/fail courses.
+    fn grade_point(&self, grade: &Grade) -> Option<f32>;
+}
+
+/// SIT standard: `Σ(credit · score) / Σcredit / 10 - 5`, namely `score / 10 - 5` for each course,
+/// and 0 for failed courses below 60.
+pub struct SitStandard;
+
+/// Common 4.0 scale: 90+ is 4.0, 80+ is 3.0, 70+ is 2.0, 60+ is 1.0.
+pub struct FourPointScale;
+
+/// World Education Services: 85+ is A (4), 75+ is B (3), 60+ is C (2).
+pub struct Wes;
+
+/// Peking University: `4 - 3 · (100 - score)² / 1600` for passed courses.
+pub struct Pku;
+
+impl GpaStrategy for SitStandard {
+    fn grade_point(&self, grade: &Grade) -> Option<f32> {
+        let score = grade.to_percentage()?;
+        if score < 60.0 {
+            return Some(0.0);
+        }
+        Some(score / 10.0 - 5.0)
+    }
+}
+
+impl GpaStrategy for FourPointScale {
+    fn grade_point(&self, grade: &Grade) -> Option<f32> {
+        let point = match grade.to_percentage()? {
+            x if x >= 90.0 => 4.0,
+            x if x >= 80.0 => 3.0,
+            x if x >= 70.0 => 2.0,
+            x if x >= 60.0 => 1.0,
+            _ => 0.0,
+        };
+        Some(point)
+    }
+}
+
+impl GpaStrategy for Wes {
+    fn grade_point(&self, grade: &Grade) -> Option<f32> {
+        let point = match grade.to_percentage()? {
+            x if x >= 85.0 => 4.0,
+            x if x >= 75.0 => 3.0,
+            x if x >= 60.0 => 2.0,
+            _ => 0.0,
+        };
+        Some(point)
+    }
+}
+
+impl GpaStrategy for Pku {
+    fn grade_point(&self, grade: &Grade) -> Option<f32> {
+        let score = grade.to_percentage()?.min(100.0);
+        if score < 60.0 {
+            return Some(0.0);
+        }
+        Some(4.0 - 3.0 * (100.0 - score).powi(2) / 1600.0)
+    }
+}
+
+/// Which attempt of a retaken course is counted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RetakePolicy {
+    /// Count every attempt
+    All,
+    /// Count the attempt with the highest grade point
+    Best,
+    /// Count the last attempt
+    Latest,
+}
+
+/// GPA of a term
+#[derive(Debug, Clone, Serialize)]
+pub struct TermGpa {
+    pub school_year: String,
+    pub semester: Semester,
+    /// `None` if no course is counted in the term
+    pub gpa: Option<f32>,
+    pub credits: f32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GpaReport {
+    /// GPA of each term, in time order
+    pub terms: Vec<TermGpa>,
+    /// `None` if no course is counted
+    pub cumulative: Option<f32>,
+    pub credits: f32,
+}
+
+/// Calculate GPA with a strategy and filters.
+pub struct GpaCalculator {
+    strategy: Box<dyn GpaStrategy>,
+    retake: RetakePolicy,
+    required_only: bool,
+    exclude_public_elective: bool,
+}
+
+impl Default for GpaCalculator {
+    fn default() -> Self {
+        Self::new(SitStandard)
+    }
+}
+
+/// Weighted average of grade points, `None` on zero credits.
+fn weighted_average<'a>(points: impl Iterator<Item = &'a (&'a Score, f32)>) -> (Option<f32>, f32) {
+    let (total, credits) = points.fold((0.0, 0.0), |(total, credits), (score, point)| {
+        (total + score.credit * point, credits + score.credit)
+    });
+    if credits > 0.0 {
+        (Some(total / credits), credits)
+    } else {
+        (None, credits)
+    }
+}
+
+impl GpaCalculator {
+    pub fn new<T: GpaStrategy + 'static>(strategy: T) -> Self {
+        GpaCalculator {
+            strategy: Box::new(strategy),
+            retake: RetakePolicy::All,
+            required_only: false,
+            exclude_public_elective: false,
+        }
+    }
+
+    pub fn retake(mut self, policy: RetakePolicy) -> Self {
+        self.retake = policy;
+        self
+    }
+
+    /// Only count required courses (必修课).
+    pub fn required_only(mut self, required_only: bool) -> Self {
+        self.required_only = required_only;
+        self
+    }
+
+    /// Do not count public elective courses (公选课).
+    pub fn exclude_public_elective(mut self, exclude: bool) -> Self {
+        self.exclude_public_elective = exclude;
+        self
+    }
+
+    fn is_counted(&self, score: &Score) -> bool {
+        if self.required_only && !score.course_nature.contains("必修") {
+            return false;
+        }
+        if self.exclude_public_elective && score.course_nature.contains("公选") {
+            return false;
+        }
+        true
+    }
+
+    /// Keep the attempts to count for each course, according to the retake policy.
+    fn select_attempts<'a>(&self, points: Vec<(&'a Score, f32)>) -> Vec<(&'a Score, f32)> {
+        if self.retake == RetakePolicy::All {
+            return points;
+        }
+        let mut selected: Vec<(&Score, f32)> = Vec::new();
+        let mut index: HashMap<&str, usize> = HashMap::new();
+        for (score, point) in points {
+            match index.get(score.course_id.as_str()) {
+                Some(&i) => {
+                    let replace = match self.retake {
+                        RetakePolicy::Best => point > selected[i].1,
+                        _ => term_key(score) >= term_key(selected[i].0),
+                    };
+                    if replace {
+                        selected[i] = (score, point);
+                    }
+                }
+                None => {
+                    index.insert(&score.course_id, selected.len());
+                    selected.push((score, point));
+                }
+            }
+        }
+        selected
+    }
+
+    pub fn calculate(&self, scores: &[Score]) -> GpaReport {
+        let points: Vec<(&Score, f32)> = scores
+            .iter()
+            .filter(|score| self.is_counted(score))
+            .filter_map(|score| Some((score, self.strategy.grade_point(&score.grade)?)))
+            .collect();
+        let points = self.select_attempts(points);
+
+        let mut term_keys: Vec<(String, Semester)> = points
+            .iter()
+            .map(|(score, _)| (score.school_year.clone(), score.semester.clone()))
+            .collect();
+        term_keys.sort_by_key(|(year, semester)| (year.clone(), semester.clone() as i32));
+        term_keys.dedup();
+
+        let terms = term_keys
+            .into_iter()
+            .map(|(school_year, semester)| {
+                let (gpa, credits) = weighted_average(points.iter().filter(|(score, _)| {
+                    score.school_year == school_year && score.semester == semester
+                }));
+                TermGpa {
+                    school_year,
+                    semester,
+                    gpa,
+                    credits,
+                }
+            })
+            .collect();
+        let (cumulative, credits) = weighted_average(points.iter());
+
+        GpaReport {
+            terms,
+            cumulative,
+            credits,
+        }
+    }
+}
+
+fn term_key(score: &Score) -> (&str, i32) {
+    (&score.school_year, score.semester.clone() as i32)
+}
+
+#[cfg(test)]
+fn test_scores() -> Vec<Score> {
+    use crate::parsers::parse_score_list_page;
+
+    let page = r#"
+{
+    "items": [
+        {"cj": "95", "kch": "A", "kcxzmc": "必修课", "xf": "4.0", "xnmmc": "2020-2021", "xqm": "3"},
+        {"cj": "55", "kch": "B", "kcxzmc": "必修课", "xf": "2.0", "xnmmc": "2020-2021", "xqm": "3"},
+        {"cj": "良好", "kch": "C", "kcxzmc": "公选课", "xf": "2.0", "xnmmc": "2020-2021", "xqm": "12"},
+        {"cj": "合格", "kch": "D", "kcxzmc": "必修课", "xf": "1.0", "xnmmc": "2020-2021", "xqm": "12"},
+        {"cj": "72", "kch": "B", "kcxzmc": "必修课", "ksxz": "重修", "xf": "2.0", "xnmmc": "2021-2022", "xqm": "3"},
+        {"cj": "不及格", "kch": "E", "kcxzmc": "必修课", "xf": "1.0", "xnmmc": "2021-2022", "xqm": "3"},
+        {"cj": "缺考", "kch": "F", "kcxzmc": "必修课", "xf": "1.0", "xnmmc": "2021-2022", "xqm": "3"}
+    ]
+}"#;
+    parse_score_list_page(page).unwrap()
+}
+
+#[test]
+fn test_gpa_strategies() {
+    let scores = test_scores();
+
+    // Failed and absent courses are 0, rather than negative.
+    for grade in ["55", "不及格", "缺考"] {
+        assert_eq!(SitStandard.grade_point(&Grade::parse(grade)), Some(0.0));
+    }
+    let report = GpaCalculator::new(SitStandard).calculate(&scores);
+    // (4 * 4.5 + 2 * 0 + 2 * 3.5 + 2 * 2.2 + 1 * 0 + 1 * 0) / 12
+    assert!((report.cumulative.unwrap() - 2.45).abs() < 1e-4);
+    assert_eq!(report.terms.len(), 3);
+    assert_eq!(report.terms[0].credits, 6.0);
+    assert_eq!(report.credits, 12.0);
+
+    let report = GpaCalculator::new(FourPointScale)
+        .retake(RetakePolicy::Best)
+        .calculate(&scores);
+    // (4 * 4 + 2 * 3 + 2 * 2 + 1 * 0 + 1 * 0) / 10
+    assert!((report.cumulative.unwrap() - 2.6).abs() < 1e-4);
+
+    let report = GpaCalculator::new(Wes)
+        .retake(RetakePolicy::Latest)
+        .exclude_public_elective(true)
+        .calculate(&scores);
+    // (4 * 4 + 2 * 2 + 1 * 0 + 1 * 0) / 8
+    assert!((report.cumulative.unwrap() - 2.5).abs() < 1e-4);
+    assert_eq!(report.terms.len(), 2);
+
+    let report = GpaCalculator::new(Pku)
+        .required_only(true)
+        .calculate(&scores[..2]);
+    assert!((report.cumulative.unwrap() - (4.0 - 3.0 * 25.0 / 1600.0) * 4.0 / 6.0).abs() < 1e-4);
+
+    let report = GpaCalculator::default().calculate(&[]);
+    assert!(report.cumulative.is_none());
+    assert!(report.terms.is_empty());
+}
@@ -1,8 +1,23 @@
 pub mod config;
 
+pub mod analysis;
+pub mod auth;
+#[cfg(feature = "sqlite")]
+pub mod cache;
+pub mod captcha;
 pub mod client;
 pub mod error;
+pub mod export;
+pub mod gpa;
+#[cfg(any(test, feature = "mock"))]
+pub mod mock;
 pub mod parsers;
+pub mod policy;
+pub mod pool;
+pub mod record;
+pub mod schedule;
 pub mod session;
+pub mod watch;
+pub mod webvpn;
 
-pub use anyhow::Result;
+pub use error::{Result, ZfError};
@@ -0,0 +1,686 @@
+//! A local stand-in of 正方教务系统, for testing without the real server.
+//!
+//! It implements the login flow (CSRF token, RSA public key, optional captcha and the encrypted
+//! password form with 302 redirects) and serves canned pages for the other endpoints, which can be
+//! replaced with `MockServer::set_page`. Pages other than the login ones redirect to the login page
+//! unless the `JSESSIONID` cookie is logged in, like the real server. `MockGateway` stands in for
+//! the WebVPN gateway in front of it (see `webvpn` module), and `MockCas` for the CAS server of
+//! unified authentication (see `auth` module).
+//!
+//! ```no_run
+//! # async fn run() -> zf_tools_rs::Result<()> {
+//! use zf_tools_rs::client::User;
+//! use zf_tools_rs::mock::MockServer;
+//! use zf_tools_rs::session::SessionBuilder;
+//!
+//! let server = MockServer::start("2018000000", "passwd").await;
+//! let session = SessionBuilder::new()
+//!     .user("2018000000")
+//!     .passwd("passwd")
+//!     .base_url(server.base_url())
+//!     .build();
+//! let client = session.login().await?;
+//! let profile = client.get_profile().await?;
+//! # Ok(())
+//! # }
+//! ```
+
+mod cas;
+mod gateway;
+
+pub use cas::{MockCas, WRONG_PASSWORD_MESSAGE as CAS_WRONG_PASSWORD_MESSAGE};
+pub use gateway::MockGateway;
+
+use crate::config::url;
+use base64::{decode, encode};
+use hyper::header::{CONTENT_TYPE, COOKIE, LOCATION, SET_COOKIE};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use rand::rngs::OsRng;
+use rsa::{PaddingScheme, PublicKeyParts, RsaPrivateKey};
+use std::collections::{HashMap, HashSet};
+use std::convert::Infallible;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::sync::oneshot;
+
+/// Path prefix of the server, the same as the real one.
+const PREFIX: &str = "/jwglxt/";
+
+/// Message shown on the login page for wrong user or password
+pub const WRONG_PASSWORD_MESSAGE: &str = "用户名或密码不正确，请重新输入！";
+/// Message shown on the login page for wrong captcha
+pub const WRONG_CAPTCHA_MESSAGE: &str = "验证码输入错误！";
+
+/// Start and end markers of a JPEG file, enough for solvers which do not read the image
+const CAPTCHA_IMAGE: &[u8] = &[0xFF, 0xD8, 0xFF, 0xD9];
+
+const INDEX_PAGE: &str =
+    r#"<html><head><title>教学管理信息服务平台</title></head><body></body></html>"#;
+
+const SCORE_LIST_PAGE: &str = r#"
+{
+    "currentPage": 1,
+    "items": [
+        {
+            "cj": "86",
+            "jd": "3.60",
+            "jxb_id": "B3F0EBAA2E6C4FD7E0530100007F3B31",
+            "kch": "B1020011",
+            "kcmc": "大学物理A1",
+            "kcxzmc": "必修课",
+            "ksxz": "正常考试",
+            "xf": "4.0",
+            "xnmmc": "2020-2021",
+            "xqm": "3"
+        },
+        {
+            "cj": "良好",
+            "jd": "3.50",
+            "jxb_id": "B3F0EBAA2E6C4FD7E0530100007F3B32",
+            "kch": "B5020021",
+            "kcmc": "音乐鉴赏",
+            "kcxzmc": "公选课",
+            "ksxz": "正常考试",
+            "xf": "2.0",
+            "xnmmc": "2020-2021",
+            "xqm": "3"
+        }
+    ],
+    "totalResult": 2
+}"#;
+
+const SCORE_DETAIL_PAGE: &str = r#"
+<table class="table table-bordered table-striped table-hover tab-bor-col-1 tab-td-padding-5" id="subtab">
+    <thead>
+        <tr><th>成绩分项</th><th>成绩比例</th><th>成绩</th></tr>
+    </thead>
+    <tbody>
+        <tr><td valign="middle">【 平时 】</td><td valign="middle">40%&nbsp;</td><td valign="middle">92&nbsp;</td></tr>
+        <tr><td valign="middle">【 期末 】</td><td valign="middle">60%&nbsp;</td><td valign="middle">82&nbsp;</td></tr>
+        <tr><td valign="middle">【 总评 】</td><td valign="middle">&nbsp;</td><td valign="middle">86&nbsp;</td></tr>
+    </tbody>
+</table>"#;
+
+const EXAM_LIST_PAGE: &str = r#"
+{
+    "currentPage": 1,
+    "items": [
+        {
+            "cdmc": "一教A204",
+            "cdxqmc": "奉贤校区",
+            "kch": "B1020011",
+            "kcmc": "大学物理A1",
+            "ksmc": "2020-2021-1期末考试",
+            "kssj": "2021-01-12(09:30-11:30)",
+            "zwh": "12"
+        }
+    ],
+    "totalResult": 1
+}"#;
+
+const TIME_TABLE_PAGE: &str = r#"
+{
+    "kbList": [
+        {
+            "cdmc": "一教A204",
+            "jcs": "1-2",
+            "jxbmc": "(2020-2021-1)-B1020011-01",
+            "jxbzc": "18104111、18104112",
+            "kch": "B1020011",
+            "kcmc": "大学物理A1",
+            "xf": "4.0",
+            "xm": "张三",
+            "xqjmc": "星期一",
+            "xqmc": "奉贤校区",
+            "zcd": "1-16周",
+            "zxs": "64"
+        },
+        {
+            "cdmc": "二教E301",
+            "jcs": "5-6",
+            "jxbmc": "(2020-2021-1)-B5020021-03",
+            "jxbzc": "",
+            "kch": "B5020021",
+            "kcmc": "音乐鉴赏",
+            "xf": "2.0",
+            "xm": "李四",
+            "xqjmc": "星期三",
+            "xqmc": "奉贤校区",
+            "zcd": "1-15周(单)",
+            "zxs": "32"
+        }
+    ]
+}"#;
+
+const PROFILE_PAGE: &str = r#"
+<html>
+<body>
+<div id="col_xh"><p class="form-control-static">2018000000</p></div>
+<div id="col_xm"><p class="form-control-static">王小明</p></div>
+<div id="col_ywxm"><p class="form-control-static">Wang Xiaoming</p></div>
+<div id="col_xbm"><p class="form-control-static">男</p></div>
+<div id="col_zjlxm"><p class="form-control-static">居民身份证</p></div>
+<div id="col_zjhm"><p class="form-control-static">310000200001010000</p></div>
+<div id="col_csrq"><p class="form-control-static">2000-01-01</p></div>
+<div id="col_mzm"><p class="form-control-static">汉族</p></div>
+<div id="col_jg"><p class="form-control-static">上海市</p></div>
+<div id="col_rxrq"><p class="form-control-static">2018-09-01</p></div>
+<div id="col_xslxdm"><p class="form-control-static">普通本科生</p></div>
+</body>
+</html>"#;
+
+const MAJOR_LIST_PAGE: &str = r#"
+[
+    {
+        "njdm": "2018",
+        "zyfx_id": "wfx",
+        "zyfxmc": "无方向",
+        "zyh": "0801",
+        "zyh_id": "0801",
+        "zymc": "化学工程与工艺"
+    }
+]"#;
+
+const CLASS_LIST_PAGE: &str = r#"
+[
+    {
+        "bh": "18104111",
+        "jgmc": "化学与环境工程学院",
+        "njmc": "2018",
+        "zyh_id": "0801",
+        "zymc": "化学工程与工艺"
+    }
+]"#;
+
+/// Path of the page without the query string, which is the key of canned pages.
+fn page_path(path: &str) -> &str {
+    path.split('?').next().unwrap_or_default()
+}
+
+fn login_page(csrf_token: &str, message: &str, captcha: bool) -> String {
+    let captcha = if captcha {
+        format!(
+            r#"<input type="text" class="form-control" name="yzm" id="yzm" value=""/>
+<img id="yzmPic" src="{}{}"/>"#,
+            PREFIX,
+            url::CAPTCHA
+        )
+    } else {
+        String::new()
+    };
+    format!(
+        r#"<html>
+<body>
+<div class="tab-content">
+<div role="tabpanel" class="tab-pane in active" id="home">
+<form class="form-horizontal" id="ajaxForm" method="post" action="{prefix}{login}">
+<input type="hidden" id="csrftoken" name="csrftoken" value="{token}"/>
+<p id="tips" class="bg_danger sl_danger">{message}</p>
+<input type="text" class="form-control" name="yhm" id="yhm" value=""/>
+<input type="password" class="form-control" name="mm" id="hidMm" value=""/>
+{captcha}
+</form>
+</div>
+</div>
+</body>
+</html>"#,
+        prefix = PREFIX,
+        login = url::LOGIN,
+        token = csrf_token,
+        message = message,
+        captcha = captcha,
+    )
+}
+
+fn response(status: StatusCode, content_type: &str, body: impl Into<Body>) -> Response<Body> {
+    let mut response = Response::new(body.into());
+    *response.status_mut() = status;
+    if let Ok(value) = content_type.parse() {
+        response.headers_mut().insert(CONTENT_TYPE, value);
+    }
+    response
+}
+
+fn redirect(location: String) -> Response<Body> {
+    let mut response = response(StatusCode::FOUND, "text/html;charset=UTF-8", String::new());
+    if let Ok(value) = location.parse() {
+        response.headers_mut().insert(LOCATION, value);
+    }
+    response
+}
+
+/// Cookie state on the server of a `JSESSIONID`
+struct MockSession {
+    csrf_token: String,
+    logged_in: bool,
+}
+
+struct State {
+    user: String,
+    passwd: String,
+    key: RsaPrivateKey,
+    sessions: HashMap<String, MockSession>,
+    /// Canned pages by path without query string
+    pages: HashMap<String, String>,
+    login_count: usize,
+    /// Number of posted login forms, including the failed ones
+    login_attempts: usize,
+    /// Answer of the captcha, `None` if captcha is not required
+    captcha: Option<String>,
+    /// Number of the next requests to fail with 503
+    failures: usize,
+    /// Tickets issued by the trusted CAS server
+    cas_tickets: Option<Arc<Mutex<HashSet<String>>>>,
+}
+
+impl State {
+    fn new(user: &str, passwd: &str) -> Self {
+        let key = RsaPrivateKey::new(&mut OsRng, 512).expect("Failed to generate RSA key");
+        let pages = [
+            (url::SCORE_LIST, SCORE_LIST_PAGE),
+            (url::SCORE_DETAIL, SCORE_DETAIL_PAGE),
+            (url::EXAM_LIST, EXAM_LIST_PAGE),
+            (url::TIME_TABLE, TIME_TABLE_PAGE),
+            (url::PROFILE, PROFILE_PAGE),
+            (url::MAJOR_LIST, MAJOR_LIST_PAGE),
+            (url::CLASS_LIST, CLASS_LIST_PAGE),
+            (url::SUGGESTED_COURSE, TIME_TABLE_PAGE),
+        ]
+        .iter()
+        .map(|(path, page)| (page_path(path).to_string(), page.to_string()))
+        .collect();
+
+        State {
+            user: user.to_string(),
+            passwd: passwd.to_string(),
+            key,
+            sessions: HashMap::new(),
+            pages,
+            login_count: 0,
+            login_attempts: 0,
+            captcha: None,
+            failures: 0,
+            cas_tickets: None,
+        }
+    }
+
+    fn new_session(&mut self) -> (String, String) {
+        let id = format!("{:032X}", rand::random::<u128>());
+        let csrf_token = format!("{:016x}", rand::random::<u64>());
+        self.sessions.insert(
+            id.clone(),
+            MockSession {
+                csrf_token: csrf_token.clone(),
+                logged_in: false,
+            },
+        );
+        (id, csrf_token)
+    }
+
+    fn decrypt_passwd(&self, encrypted: &str) -> Option<String> {
+        let data = decode(encrypted).ok()?;
+        let passwd = self
+            .key
+            .decrypt(PaddingScheme::new_pkcs1v15_encrypt(), &data)
+            .ok()?;
+        String::from_utf8(passwd).ok()
+    }
+
+    fn login(
+        &mut self,
+        session_id: Option<&str>,
+        form: &HashMap<String, String>,
+    ) -> Response<Body> {
+        let field = |name: &str| form.get(name).map(String::as_str).unwrap_or_default();
+        let session = match session_id.and_then(|id| self.sessions.get(id)) {
+            Some(session) if session.csrf_token == field("csrftoken") => session,
+            Some(session) => {
+                let page = login_page(
+                    &session.csrf_token,
+                    "登录超时，请重新登录！",
+                    self.captcha.is_some(),
+                );
+                return response(StatusCode::OK, "text/html;charset=UTF-8", page);
+            }
+            None => return redirect(format!("{}{}", PREFIX, url::LOGIN)),
+        };
+        let csrf_token = session.csrf_token.clone();
+        self.login_attempts += 1;
+
+        if let Some(answer) = &self.captcha {
+            if field("yzm") != answer {
+                let page = login_page(&csrf_token, WRONG_CAPTCHA_MESSAGE, true);
+                return response(StatusCode::OK, "text/html;charset=UTF-8", page);
+            }
+        }
+        let passwd = self.decrypt_passwd(field("mm"));
+        if field("yhm") != self.user || passwd.as_deref() != Some(self.passwd.as_str()) {
+            let page = login_page(&csrf_token, WRONG_PASSWORD_MESSAGE, self.captcha.is_some());
+            return response(StatusCode::OK, "text/html;charset=UTF-8", page);
+        }
+
+        if let Some(session) = session_id.and_then(|id| self.sessions.get_mut(id)) {
+            session.logged_in = true;
+        }
+        self.login_count += 1;
+        redirect(format!("{}{}?jsdm=xs", PREFIX, url::INDEX))
+    }
+
+    /// Accept the ticket from the CAS server, and login a new session.
+    fn cas_login(&mut self, form: &HashMap<String, String>) -> Response<Body> {
+        let ticket = form.get("ticket").map(String::as_str).unwrap_or_default();
+        let accepted = self.cas_tickets.as_ref().is_some_and(|tickets| {
+            tickets
+                .lock()
+                .expect("Mock CAS tickets poisoned")
+                .remove(ticket)
+        });
+        if !accepted {
+            return redirect(format!("{}{}", PREFIX, url::LOGIN));
+        }
+
+        let (id, _) = self.new_session();
+        if let Some(session) = self.sessions.get_mut(&id) {
+            session.logged_in = true;
+        }
+        self.login_count += 1;
+        let mut response = redirect(format!("{}{}?jsdm=xs", PREFIX, url::INDEX));
+        let cookie = format!("JSESSIONID={}; Path=/jwglxt; HttpOnly", id);
+        if let Ok(value) = cookie.parse() {
+            response.headers_mut().insert(SET_COOKIE, value);
+        }
+        response
+    }
+
+    fn route(
+        &mut self,
+        method: &Method,
+        path: &str,
+        session_id: Option<&str>,
+        form: &HashMap<String, String>,
+    ) -> Response<Body> {
+        if self.failures > 0 {
+            self.failures -= 1;
+            return response(StatusCode::SERVICE_UNAVAILABLE, "text/html", String::new());
+        }
+        if path == url::LOGIN {
+            if method == Method::POST {
+                return self.login(session_id, form);
+            }
+            let (id, csrf_token) = self.new_session();
+            let mut response = response(
+                StatusCode::OK,
+                "text/html;charset=UTF-8",
+                login_page(&csrf_token, "", self.captcha.is_some()),
+            );
+            let cookie = format!("JSESSIONID={}; Path=/jwglxt; HttpOnly", id);
+            if let Ok(value) = cookie.parse() {
+                response.headers_mut().insert(SET_COOKIE, value);
+            }
+            return response;
+        }
+        if path == url::CAPTCHA {
+            return response(StatusCode::OK, "image/jpeg", CAPTCHA_IMAGE);
+        }
+        if path == url::CAS_SERVICE {
+            return self.cas_login(form);
+        }
+        if path == url::RSA_PUBLIC_KEY {
+            let public_key = serde_json::json!({
+                "modulus": encode(self.key.n().to_bytes_be()),
+                "exponent": encode(self.key.e().to_bytes_be()),
+            });
+            return response(
+                StatusCode::OK,
+                "application/json;charset=UTF-8",
+                public_key.to_string(),
+            );
+        }
+
+        let logged_in = session_id
+            .and_then(|id| self.sessions.get(id))
+            .is_some_and(|session| session.logged_in);
+        if !logged_in {
+            return redirect(format!("{}{}", PREFIX, url::LOGIN));
+        }
+        if path == url::INDEX {
+            return response(
+                StatusCode::OK,
+                "text/html;charset=UTF-8",
+                INDEX_PAGE.to_string(),
+            );
+        }
+        match self.pages.get(path) {
+            Some(page) => response(StatusCode::OK, "text/html;charset=UTF-8", page.clone()),
+            None => response(StatusCode::NOT_FOUND, "text/html", String::new()),
+        }
+    }
+}
+
+fn parse_form(data: &[u8], form: &mut HashMap<String, String>) {
+    form.extend(::url::form_urlencoded::parse(data).into_owned());
+}
+
+fn session_id(request: &Request<Body>) -> Option<String> {
+    request
+        .headers()
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(name, _)| *name == "JSESSIONID")
+        .map(|(_, value)| value.to_string())
+}
+
+async fn handle(
+    state: Arc<Mutex<State>>,
+    request: Request<Body>,
+) -> std::result::Result<Response<Body>, hyper::Error> {
+    let method = request.method().clone();
+    let session_id = session_id(&request);
+    let uri = request.uri().clone();
+    let body = hyper::body::to_bytes(request.into_body()).await?;
+
+    // The client sends form data in the body even on GET requests.
+    let mut form = HashMap::new();
+    parse_form(uri.query().unwrap_or_default().as_bytes(), &mut form);
+    parse_form(&body, &mut form);
+
+    let path = match uri.path().strip_prefix(PREFIX) {
+        Some(path) => path,
+        None => return Ok(response(StatusCode::NOT_FOUND, "text/html", String::new())),
+    };
+    let mut state = state.lock().expect("Mock server state poisoned");
+    Ok(state.route(&method, path, session_id.as_deref(), &form))
+}
+
+/// Serve on a random local port, until the returned sender is used or dropped.
+fn spawn_server<S, F>(
+    state: Arc<Mutex<S>>,
+    handler: fn(Arc<Mutex<S>>, Request<Body>) -> F,
+) -> (SocketAddr, oneshot::Sender<()>)
+where
+    S: Send + 'static,
+    F: Future<Output = std::result::Result<Response<Body>, hyper::Error>> + Send + 'static,
+{
+    let make_service = make_service_fn(move |_| {
+        let state = state.clone();
+        async move { Ok::<_, Infallible>(service_fn(move |request| handler(state.clone(), request))) }
+    });
+    let server = Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(make_service);
+    let addr = server.local_addr();
+
+    let (shutdown, receiver) = oneshot::channel::<()>();
+    let server = server.with_graceful_shutdown(async {
+        receiver.await.ok();
+    });
+    tokio::spawn(server);
+    (addr, shutdown)
+}
+
+/// A running mock server, which shuts down on drop.
+pub struct MockServer {
+    addr: SocketAddr,
+    state: Arc<Mutex<State>>,
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+impl MockServer {
+    /// Start the server on a random local port, with the only account `user`.
+    pub async fn start(user: &str, passwd: &str) -> MockServer {
+        let state = Arc::new(Mutex::new(State::new(user, passwd)));
+        let (addr, shutdown) = spawn_server(state.clone(), handle);
+        MockServer {
+            addr,
+            state,
+            shutdown: Some(shutdown),
+        }
+    }
+
+    /// Base url to use in `SessionBuilder::base_url`.
+    pub fn base_url(&self) -> String {
+        format!("http://{}{}", self.addr, PREFIX)
+    }
+
+    /// Replace the page of the path in `config::url`. Query string in the path is ignored.
+    pub fn set_page<T: ToString>(&self, path: &str, page: T) {
+        let mut state = self.state.lock().expect("Mock server state poisoned");
+        state
+            .pages
+            .insert(page_path(path).to_string(), page.to_string());
+    }
+
+    /// Log out all the sessions, as if they are expired.
+    pub fn expire_sessions(&self) {
+        let mut state = self.state.lock().expect("Mock server state poisoned");
+        state.sessions.clear();
+    }
+
+    /// Service URL of the unified authentication, see `auth::CasLogin::service`.
+    pub fn cas_service(&self) -> String {
+        format!("{}{}", self.base_url(), url::CAS_SERVICE)
+    }
+
+    /// Accept the tickets issued by the CAS server on `cas_service`.
+    pub fn trust_cas(&self, cas: &MockCas) {
+        let mut state = self.state.lock().expect("Mock server state poisoned");
+        state.cas_tickets = Some(cas.tickets());
+    }
+
+    /// Show a captcha on the login page, and reject logins without the answer.
+    pub fn require_captcha(&self, answer: &str) {
+        let mut state = self.state.lock().expect("Mock server state poisoned");
+        state.captcha = Some(answer.to_string());
+    }
+
+    /// Fail the next `count` requests with 503, like the server under load.
+    pub fn fail_next(&self, count: usize) {
+        let mut state = self.state.lock().expect("Mock server state poisoned");
+        state.failures = count;
+    }
+
+    /// Change the password of the account, like the user did on another device.
+    pub fn set_passwd(&self, passwd: &str) {
+        let mut state = self.state.lock().expect("Mock server state poisoned");
+        state.passwd = passwd.to_string();
+    }
+
+    /// Number of successful logins
+    pub fn login_count(&self) -> usize {
+        self.state
+            .lock()
+            .expect("Mock server state poisoned")
+            .login_count
+    }
+
+    /// Number of login forms posted, including the failed ones
+    pub fn login_attempts(&self) -> usize {
+        self.state
+            .lock()
+            .expect("Mock server state poisoned")
+            .login_attempts
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            shutdown.send(()).ok();
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_mock_server() {
+    use crate::client::{Environment, User};
+    use crate::parsers::{SchoolYear, Semester};
+    use crate::session::SessionBuilder;
+    use crate::ZfError;
+
+    let server = MockServer::start("2018000000", "passwd").await;
+
+    let session = SessionBuilder::new()
+        .user("2018000000")
+        .passwd("wrong")
+        .base_url(server.base_url())
+        .build();
+    match session.login().await {
+        Err(ZfError::AuthenticationFailed(message)) => {
+            assert_eq!(message, WRONG_PASSWORD_MESSAGE)
+        }
+        r => panic!("Unexpected login result: {:?}", r.map(|_| ())),
+    }
+
+    let session = SessionBuilder::new()
+        .user("2018000000")
+        .passwd("passwd")
+        .base_url(server.base_url())
+        .build();
+    let client = session.login().await.unwrap();
+    assert!(client.is_valid().await.unwrap());
+
+    let profile = client.get_profile().await.unwrap();
+    assert!(serde_json::to_string(&profile).unwrap().contains("王小明"));
+    let scores = client
+        .get_score_list(SchoolYear::AllYear, Semester::All)
+        .await
+        .unwrap();
+    assert_eq!(scores.len(), 2);
+    let components = client.get_score_detail(&scores[0]).await.unwrap();
+    assert_eq!(components.len(), 3);
+    let timetable = client
+        .get_timetable(SchoolYear::SomeYear(2020), Semester::FirstTerm)
+        .await
+        .unwrap();
+    assert_eq!(timetable[0].course_name, "大学物理A1");
+    let exams = client
+        .get_exam_list(SchoolYear::SomeYear(2020), Semester::FirstTerm)
+        .await
+        .unwrap();
+    assert_eq!(exams.len(), 1);
+    let majors = client
+        .get_major_list(SchoolYear::SomeYear(2018))
+        .await
+        .unwrap();
+    assert_eq!(majors.len(), 1);
+    let classes = client
+        .get_class_list(SchoolYear::SomeYear(2018), Semester::FirstTerm)
+        .await
+        .unwrap();
+    assert_eq!(classes.len(), 1);
+    assert_eq!(server.login_count(), 1);
+
+    // The client logs in again after the session expired.
+    server.expire_sessions();
+    assert!(!client.is_valid().await.unwrap());
+    server.set_page(url::SCORE_LIST, r#"{"items": []}"#);
+    let scores = client
+        .get_score_list(SchoolYear::AllYear, Semester::All)
+        .await
+        .unwrap();
+    assert!(scores.is_empty());
+    assert_eq!(server.login_count(), 2);
+}
@@ -0,0 +1,174 @@
+use super::{parse_form, redirect, response, spawn_server};
+use aes::cipher::generic_array::GenericArray;
+use aes::cipher::{BlockDecrypt, KeyInit};
+use aes::Aes128;
+use base64::decode;
+use hyper::{Body, Method, Request, Response, StatusCode};
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::sync::oneshot;
+
+/// Path prefix of the CAS server
+const PREFIX: &str = "/authserver/";
+/// Salt of the password, which changes on every page of the real server
+const SALT: &str = "rjBFAaHsNkKAhpoi";
+
+/// Message shown on the CAS login page for wrong user or password
+pub const WRONG_PASSWORD_MESSAGE: &str = "您提供的用户名或者密码有误";
+
+fn login_page(lt: &str, message: &str) -> String {
+    format!(
+        r#"<html>
+<body>
+<form id="casLoginForm" class="fm-v clearfix amp-login-form" method="post" action="">
+<input type="text" id="username" name="username" value=""/>
+<input type="password" id="password" name="password" value=""/>
+<span id="msg" class="auth_error">{message}</span>
+<input type="hidden" name="lt" value="{lt}"/>
+<input type="hidden" name="dllt" value="userNamePasswordLogin"/>
+<input type="hidden" name="execution" value="e1s1"/>
+<input type="hidden" name="_eventId" value="submit"/>
+<input type="hidden" name="rmShown" value="1"/>
+<input type="hidden" id="pwdDefaultEncryptSalt" value="{salt}"/>
+</form>
+</body>
+</html>"#,
+        lt = lt,
+        message = message,
+        salt = SALT,
+    )
+}
+
+/// Decrypt the password encrypted by the page script, which is prefixed by 64 random chars.
+fn decrypt_passwd(encrypted: &str) -> Option<String> {
+    let data = decode(encrypted).ok()?;
+    if data.len() < 80 || data.len() % 16 != 0 {
+        return None;
+    }
+    let cipher = Aes128::new(GenericArray::from_slice(SALT.as_bytes()));
+    let mut plain = Vec::with_capacity(data.len());
+    // The first block is garbled without the IV, but it is the random prefix anyway.
+    let mut last = GenericArray::from([0u8; 16]);
+    for block in data.chunks(16) {
+        let mut decrypted = GenericArray::clone_from_slice(block);
+        cipher.decrypt_block(&mut decrypted);
+        decrypted.iter_mut().zip(&last).for_each(|(a, b)| *a ^= b);
+        plain.extend_from_slice(&decrypted);
+        last = GenericArray::clone_from_slice(block);
+    }
+    let padding = *plain.last()? as usize;
+    plain.truncate(plain.len().checked_sub(padding)?);
+    String::from_utf8(plain.get(64..)?.to_vec()).ok()
+}
+
+struct CasState {
+    user: String,
+    passwd: String,
+    /// Login tickets on the pages, each is used once
+    login_tickets: HashSet<String>,
+    /// Service tickets, shared with the trusting `MockServer`
+    tickets: Arc<Mutex<HashSet<String>>>,
+}
+
+impl CasState {
+    fn new_login_page(&mut self, message: &str) -> Response<Body> {
+        let lt = format!("LT-{:016x}-cas", rand::random::<u64>());
+        self.login_tickets.insert(lt.clone());
+        response(
+            StatusCode::OK,
+            "text/html;charset=UTF-8",
+            login_page(&lt, message),
+        )
+    }
+
+    fn login(&mut self, form: &HashMap<String, String>) -> Response<Body> {
+        let field = |name: &str| form.get(name).map(String::as_str).unwrap_or_default();
+        if !self.login_tickets.remove(field("lt")) || field("execution").is_empty() {
+            return self.new_login_page("");
+        }
+        let passwd = decrypt_passwd(field("password"));
+        if field("username") != self.user || passwd.as_deref() != Some(self.passwd.as_str()) {
+            return self.new_login_page(WRONG_PASSWORD_MESSAGE);
+        }
+
+        let service = field("service");
+        let ticket = format!("ST-{:016x}-cas", rand::random::<u64>());
+        self.tickets
+            .lock()
+            .expect("Mock CAS tickets poisoned")
+            .insert(ticket.clone());
+        let separator = if service.contains('?') { '&' } else { '?' };
+        redirect(format!("{}{}ticket={}", service, separator, ticket))
+    }
+}
+
+async fn handle(
+    state: Arc<Mutex<CasState>>,
+    request: Request<Body>,
+) -> std::result::Result<Response<Body>, hyper::Error> {
+    let method = request.method().clone();
+    let uri = request.uri().clone();
+    let body = hyper::body::to_bytes(request.into_body()).await?;
+    let mut form = HashMap::new();
+    parse_form(uri.query().unwrap_or_default().as_bytes(), &mut form);
+    parse_form(&body, &mut form);
+
+    if uri.path() != format!("{}login", PREFIX) {
+        return Ok(response(StatusCode::NOT_FOUND, "text/html", String::new()));
+    }
+    let mut state = state.lock().expect("Mock CAS state poisoned");
+    Ok(match method {
+        Method::POST => state.login(&form),
+        _ => state.new_login_page(""),
+    })
+}
+
+/// A local stand-in of the CAS server, which shuts down on drop.
+///
+/// The login page asks for the salted password like the real one, and redirects to the service
+/// with a ticket after login. Let the `MockServer` accept the tickets with `MockServer::trust_cas`.
+pub struct MockCas {
+    addr: SocketAddr,
+    state: Arc<Mutex<CasState>>,
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+impl MockCas {
+    /// Start the server on a random local port, with the only account `user`.
+    pub async fn start(user: &str, passwd: &str) -> MockCas {
+        let state = Arc::new(Mutex::new(CasState {
+            user: user.to_string(),
+            passwd: passwd.to_string(),
+            login_tickets: HashSet::new(),
+            tickets: Arc::default(),
+        }));
+        let (addr, shutdown) = spawn_server(state.clone(), handle);
+        MockCas {
+            addr,
+            state,
+            shutdown: Some(shutdown),
+        }
+    }
+
+    /// Server address to use in `CasLogin::new`.
+    pub fn base_url(&self) -> String {
+        format!("http://{}{}", self.addr, PREFIX)
+    }
+
+    pub(crate) fn tickets(&self) -> Arc<Mutex<HashSet<String>>> {
+        self.state
+            .lock()
+            .expect("Mock CAS state poisoned")
+            .tickets
+            .clone()
+    }
+}
+
+impl Drop for MockCas {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            shutdown.send(()).ok();
+        }
+    }
+}
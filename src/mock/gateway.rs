@@ -0,0 +1,229 @@
+use super::{parse_form, redirect, response, spawn_server};
+use crate::webvpn::WebVpn;
+use hyper::header::{CONTENT_TYPE, COOKIE, HOST, LOCATION, SET_COOKIE};
+use hyper::{Body, Method, Request, Response, StatusCode};
+use reqwest::Url;
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::sync::oneshot;
+
+/// Cookie of the portal session
+const TICKET_COOKIE: &str = "wengine_vpn_ticket";
+
+const PORTAL_PAGE: &str = r#"<html>
+<body>
+<form id="login-form" method="post" action="/do-login">
+<input type="hidden" name="auth_type" value="local"/>
+<input type="text" name="username"/>
+<input type="password" name="password"/>
+</form>
+</body>
+</html>"#;
+
+struct GatewayState {
+    user: String,
+    passwd: String,
+    tickets: HashSet<String>,
+    login_count: usize,
+}
+
+fn ticket(request: &Request<Body>) -> Option<String> {
+    request
+        .headers()
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(name, _)| *name == TICKET_COOKIE)
+        .map(|(_, value)| value.to_string())
+}
+
+fn portal_login(state: &mut GatewayState, form: &HashMap<String, String>) -> Response<Body> {
+    let field = |name: &str| form.get(name).map(String::as_str).unwrap_or_default();
+    if field("username") != state.user || field("password") != state.passwd {
+        let result = serde_json::json!({
+            "success": false,
+            "error": "INVALID_ACCOUNT",
+            "message": "用户名或密码错误",
+        });
+        return response(StatusCode::OK, "application/json", result.to_string());
+    }
+
+    let ticket = format!("{:016x}", rand::random::<u64>());
+    state.tickets.insert(ticket.clone());
+    state.login_count += 1;
+    let result = serde_json::json!({"success": true, "url": "/", "error": "", "message": ""});
+    let mut response = response(StatusCode::OK, "application/json", result.to_string());
+    if let Ok(value) = format!("{}={}; Path=/", TICKET_COOKIE, ticket).parse() {
+        response.headers_mut().insert(SET_COOKIE, value);
+    }
+    response
+}
+
+/// Send the request to the site, and rewrite the redirections through the gateway.
+async fn forward(
+    webvpn: &WebVpn,
+    target: Url,
+    request: Request<Body>,
+) -> std::result::Result<Response<Body>, hyper::Error> {
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .expect("Failed to build the gateway client");
+    let method =
+        reqwest::Method::from_bytes(request.method().as_str().as_bytes()).expect("Invalid method");
+    let mut forwarded = client.request(method, target.clone());
+    for name in &[COOKIE, CONTENT_TYPE] {
+        if let Some(value) = request.headers().get(name).and_then(|v| v.to_str().ok()) {
+            forwarded = forwarded.header(name.as_str(), value);
+        }
+    }
+    let body = hyper::body::to_bytes(request.into_body()).await?;
+    let site_response = match forwarded.body(body.to_vec()).send().await {
+        Ok(site_response) => site_response,
+        Err(e) => {
+            return Ok(response(
+                StatusCode::BAD_GATEWAY,
+                "text/plain",
+                e.to_string(),
+            ))
+        }
+    };
+
+    let status =
+        StatusCode::from_u16(site_response.status().as_u16()).unwrap_or(StatusCode::BAD_GATEWAY);
+    let mut headers = Vec::new();
+    for (name, value) in site_response.headers() {
+        let value = match value.to_str() {
+            Ok(value) => value.to_string(),
+            Err(_) => continue,
+        };
+        match name.as_str() {
+            "content-type" | "set-cookie" => headers.push((name.as_str().to_string(), value)),
+            "location" => {
+                let location = target.join(&value).ok();
+                if let Some(location) = location.and_then(|url| webvpn.rewrite(&url).ok()) {
+                    headers.push((LOCATION.as_str().to_string(), location.to_string()));
+                }
+            }
+            _ => {}
+        }
+    }
+    let body = site_response.bytes().await.unwrap_or_default();
+
+    let mut response = Response::new(Body::from(body));
+    *response.status_mut() = status;
+    for (name, value) in headers {
+        if let (Ok(name), Ok(value)) = (name.parse::<hyper::header::HeaderName>(), value.parse()) {
+            response.headers_mut().append(name, value);
+        }
+    }
+    Ok(response)
+}
+
+async fn handle(
+    state: Arc<Mutex<GatewayState>>,
+    request: Request<Body>,
+) -> std::result::Result<Response<Body>, hyper::Error> {
+    let host = request
+        .headers()
+        .get(HOST)
+        .and_then(|host| host.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    let gateway = format!("http://{}/", host);
+    let path = request.uri().path().trim_start_matches('/').to_string();
+
+    if path == "login" {
+        return Ok(response(
+            StatusCode::OK,
+            "text/html;charset=UTF-8",
+            PORTAL_PAGE,
+        ));
+    }
+    if path == "do-login" && request.method() == Method::POST {
+        let body = hyper::body::to_bytes(request.into_body()).await?;
+        let mut form = HashMap::new();
+        parse_form(&body, &mut form);
+        let mut state = state.lock().expect("Mock gateway state poisoned");
+        return Ok(portal_login(&mut state, &form));
+    }
+
+    let logged_in = {
+        let state = state.lock().expect("Mock gateway state poisoned");
+        ticket(&request).is_some_and(|ticket| state.tickets.contains(&ticket))
+    };
+    if !logged_in {
+        return Ok(redirect("/login".to_string()));
+    }
+
+    let webvpn = WebVpn::new(&gateway).expect("Invalid gateway address");
+    let target = Url::parse(&format!(
+        "{}{}",
+        gateway.trim_end_matches('/'),
+        request.uri()
+    ))
+    .ok()
+    .and_then(|url| webvpn.restore(&url));
+    match target {
+        Some(target) => forward(&webvpn, target, request).await,
+        None => Ok(response(StatusCode::NOT_FOUND, "text/html", String::new())),
+    }
+}
+
+/// A local stand-in of the WebVPN gateway, which shuts down on drop.
+///
+/// The portal accepts the only account `user`, and forwards the requests of logged-in portal
+/// sessions to the sites, like `MockServer`.
+pub struct MockGateway {
+    addr: SocketAddr,
+    state: Arc<Mutex<GatewayState>>,
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+impl MockGateway {
+    /// Start the gateway on a random local port.
+    pub async fn start(user: &str, passwd: &str) -> MockGateway {
+        let state = Arc::new(Mutex::new(GatewayState {
+            user: user.to_string(),
+            passwd: passwd.to_string(),
+            tickets: HashSet::new(),
+            login_count: 0,
+        }));
+        let (addr, shutdown) = spawn_server(state.clone(), handle);
+        MockGateway {
+            addr,
+            state,
+            shutdown: Some(shutdown),
+        }
+    }
+
+    /// Gateway address to use in `WebVpn::new`.
+    pub fn base_url(&self) -> String {
+        format!("http://{}/", self.addr)
+    }
+
+    /// Log out all the portal sessions, as if they are expired.
+    pub fn expire_sessions(&self) {
+        let mut state = self.state.lock().expect("Mock gateway state poisoned");
+        state.tickets.clear();
+    }
+
+    /// Number of successful portal logins
+    pub fn login_count(&self) -> usize {
+        self.state
+            .lock()
+            .expect("Mock gateway state poisoned")
+            .login_count
+    }
+}
+
+impl Drop for MockGateway {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            shutdown.send(()).ok();
+        }
+    }
+}
@@ -1,22 +1,33 @@
 mod classes;
+mod exam;
 mod profile;
 mod score;
 mod select_course;
 mod timetable;
 
 pub use classes::{parse_class_list_page, parse_major_list_page};
+pub use exam::parse_exam_list_page;
 pub use profile::parse_profile_page;
-pub use score::{calculate_gpa, parse_score_list_page};
-pub use select_course::parse_available_course_page;
+pub use score::{calculate_gpa, parse_score_detail_page, parse_score_list_page};
+pub use select_course::{
+    parse_available_course_page, parse_drop_result, parse_select_result, parse_selected_class_page,
+    parse_selection_round_page, parse_teaching_class_page,
+};
 pub use timetable::parse_timetable_page;
 
 pub use classes::{Class, Major};
+pub use exam::Exam;
 pub use profile::Profile;
-pub use score::Score;
-pub use select_course::SelectCourse;
-pub use timetable::Course;
+pub use score::{ExamNature, FiveLevel, Grade, Score, ScoreComponent};
+pub use select_course::{
+    CourseType, SelectCourse, SelectedClass, SelectionResult, SelectionRound, TeachingClass,
+};
+pub use timetable::{Course, PeriodRange, WeekSet, Weekday};
 
+use crate::error::{Result, ZfError};
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::fmt;
 
 #[derive(Clone)]
 pub enum SchoolYear {
@@ -24,16 +35,16 @@ pub enum SchoolYear {
     SomeYear(i32),
 }
 
-impl ToString for SchoolYear {
-    fn to_string(&self) -> String {
+impl fmt::Display for SchoolYear {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            SchoolYear::SomeYear(year) => year.to_string(),
-            SchoolYear::AllYear => String::new(),
+            SchoolYear::SomeYear(year) => write!(f, "{}", year),
+            SchoolYear::AllYear => Ok(()),
         }
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Semester {
     All = 0,
     FirstTerm = 1,
@@ -51,29 +62,32 @@ impl Semester {
         }
     }
 
-    fn from_raw(raw: &str) -> Result<Semester, ParserError> {
+    fn from_raw(raw: &str) -> Result<Semester> {
         match raw {
             "" => Ok(Semester::All),
             "3" => Ok(Semester::FirstTerm),
             "12" => Ok(Semester::SecondTerm),
             "16" => Ok(Semester::MidTerm),
-            _ => Err(ParserError::SemesterError),
+            _ => Err(ZfError::parse("xqm", format!("unknown semester {:?}", raw))),
         }
     }
 }
 
 pub fn get_str(x: Option<&Value>) -> String {
-    String::from(x.map(|m| m.as_str().unwrap()).unwrap_or_default())
-}
-
-pub fn get_f32(x: Option<&Value>) -> f32 {
-    get_str(x).parse().unwrap()
+    match x {
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Null) | None => String::new(),
+        Some(v) => v.to_string(),
+    }
 }
 
-#[derive(Debug, thiserror::Error)]
-pub enum ParserError {
-    #[error("Profile element is wrong!!")]
-    MissingField,
-    #[error("Invalid semester valid given.")]
-    SemesterError,
+/// Get a number field which may be given in string, empty value is regarded as 0.
+pub fn get_f32(x: &Value, field: &str) -> Result<f32> {
+    let value = get_str(x.get(field));
+    if value.is_empty() {
+        return Ok(0.0);
+    }
+    value
+        .parse()
+        .map_err(|_| ZfError::parse(field, format!("{:?} is not a number", value)))
 }
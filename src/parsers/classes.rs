@@ -1,4 +1,4 @@
-use crate::Result;
+use crate::error::{Result, ZfError};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
@@ -47,19 +47,23 @@ pub struct Class {
     class_id: String,
 }
 
+fn parse_year(field: &str, year: &str) -> Result<i32> {
+    year.parse()
+        .map_err(|_| ZfError::parse(field, format!("invalid year {:?}", year)))
+}
+
 pub fn parse_major_list_page(page: &str) -> Result<Vec<Major>> {
     let json_page: Value = serde_json::from_str(page)?;
 
     if let Some(major_list) = json_page.as_array() {
-        let result = major_list
+        return major_list
             .iter()
             .map(|v| {
-                let mut x = serde_json::from_value::<Major>(v.clone()).unwrap();
-                x.entrance_year = x._entrance_year.parse().unwrap();
-                x
+                let mut x = serde_json::from_value::<Major>(v.clone())?;
+                x.entrance_year = parse_year("njdm", &x._entrance_year)?;
+                Ok(x)
             })
             .collect();
-        return Ok(result);
     }
     Ok(vec![])
 }
@@ -68,15 +72,14 @@ pub fn parse_class_list_page(page: &str) -> Result<Vec<Class>> {
     let json_page: Value = serde_json::from_str(page)?;
 
     if let Some(major_list) = json_page.as_array() {
-        let result = major_list
+        return major_list
             .iter()
             .map(|v| {
-                let mut x = serde_json::from_value::<Class>(v.clone()).unwrap();
-                x.grade = x._grade.parse().unwrap();
-                x
+                let mut x = serde_json::from_value::<Class>(v.clone())?;
+                x.grade = parse_year("njmc", &x._grade)?;
+                Ok(x)
             })
             .collect();
-        return Ok(result);
     }
     Ok(vec![])
 }
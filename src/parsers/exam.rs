@@ -0,0 +1,201 @@
+use crate::error::{Result, ZfError};
+use crate::parsers::get_str;
+use chrono::{NaiveDate, NaiveTime};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+lazy_static::lazy_static! {
+    static ref EXAM_TIME_REGEX: Regex =
+        Regex::new(r"(\d{4}-\d{2}-\d{2})\((\d{1,2}:\d{2})-(\d{1,2}:\d{2})\)").unwrap();
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Exam {
+    /// 课程名称
+    course: String,
+    /// 课程代码
+    course_id: String,
+    /// 考试日期, `None` if the exam is not scheduled yet
+    date: Option<NaiveDate>,
+    /// 开始时间
+    start_time: Option<NaiveTime>,
+    /// 结束时间
+    end_time: Option<NaiveTime>,
+    /// 考场
+    place: String,
+    /// 座位号
+    seat_no: String,
+    /// 校区
+    campus: String,
+    /// 考试名称, 如期末考试、补考
+    exam_type: String,
+}
+
+impl Exam {
+    pub fn course(&self) -> &str {
+        &self.course
+    }
+
+    pub fn course_id(&self) -> &str {
+        &self.course_id
+    }
+
+    pub fn date(&self) -> Option<NaiveDate> {
+        self.date
+    }
+
+    pub fn start_time(&self) -> Option<NaiveTime> {
+        self.start_time
+    }
+
+    pub fn end_time(&self) -> Option<NaiveTime> {
+        self.end_time
+    }
+
+    pub fn place(&self) -> &str {
+        &self.place
+    }
+
+    pub fn seat_no(&self) -> &str {
+        &self.seat_no
+    }
+
+    pub fn campus(&self) -> &str {
+        &self.campus
+    }
+
+    pub fn exam_type(&self) -> &str {
+        &self.exam_type
+    }
+}
+
+type ExamTime = (NaiveDate, NaiveTime, NaiveTime);
+
+/// Parse exam time like "2021-01-12(09:00-11:00)". Exams not scheduled yet have an empty time or
+/// a text like "待定", which are `None`.
+fn parse_exam_time(time: &str) -> Result<Option<ExamTime>> {
+    let captures = match EXAM_TIME_REGEX.captures(time) {
+        Some(captures) => captures,
+        None => return Ok(None),
+    };
+    let invalid = |_| ZfError::parse("kssj", format!("invalid exam time {:?}", time));
+    let date = NaiveDate::parse_from_str(&captures[1], "%Y-%m-%d").map_err(invalid)?;
+    let start_time = NaiveTime::parse_from_str(&captures[2], "%H:%M").map_err(invalid)?;
+    let end_time = NaiveTime::parse_from_str(&captures[3], "%H:%M").map_err(invalid)?;
+    Ok(Some((date, start_time, end_time)))
+}
+
+pub fn parse_exam_list_page(page: &str) -> Result<Vec<Exam>> {
+    let json_page: Value = serde_json::from_str(page)?;
+
+    if let Some(exam_list) = json_page["items"].as_array() {
+        let mut result = Vec::new();
+        for exam in exam_list {
+            let time = parse_exam_time(&get_str(exam.get("kssj")))?;
+            result.push(Exam {
+                course: get_str(exam.get("kcmc")),
+                course_id: get_str(exam.get("kch")),
+                date: time.map(|(date, _, _)| date),
+                start_time: time.map(|(_, start_time, _)| start_time),
+                end_time: time.map(|(_, _, end_time)| end_time),
+                place: get_str(exam.get("cdmc")),
+                seat_no: get_str(exam.get("zwh")),
+                campus: get_str(exam.get("cdxqmc")),
+                exam_type: get_str(exam.get("ksmc")),
+            });
+        }
+        return Ok(result);
+    }
+    Ok(vec![])
+}
+
+#[test]
+fn test_parse_exam_list_page() {
+    let page = r#"
+{
+    "currentPage": 1,
+    "currentResult": 0,
+    "entityOrField": false,
+    "items": [
+        {
+            "cdbh": "A204",
+            "cdjc": "一教A204",
+            "cdmc": "一教A204",
+            "cdxqmc": "奉贤校区",
+            "jxbmc": "(2020-2021-1)-B1020011-02",
+            "jxbzc": "19104211,19104212",
+            "kch": "B1020011",
+            "kcmc": "大学物理A1",
+            "ksmc": "2020-2021-1期末考试",
+            "kssj": "2021-01-12(09:30-11:30)",
+            "row_id": "1",
+            "totalresult": "2",
+            "xf": "4.0",
+            "xnm": "2020",
+            "xnmc": "2020-2021",
+            "xqm": "3",
+            "xqmc": "1",
+            "zwh": "17"
+        },
+        {
+            "cdbh": "B305",
+            "cdjc": "二教B305",
+            "cdmc": "二教B305",
+            "cdxqmc": "奉贤校区",
+            "jxbmc": "(2020-2021-1)-B2041007-01",
+            "jxbzc": "19104211",
+            "kch": "B2041007",
+            "kcmc": "数据结构",
+            "ksmc": "2020-2021-1期末考试",
+            "kssj": "2021-01-14(13:00-15:00)",
+            "row_id": "2",
+            "totalresult": "2",
+            "xf": "3.0",
+            "xnm": "2020",
+            "xnmc": "2020-2021",
+            "xqm": "3",
+            "xqmc": "1",
+            "zwh": "5"
+        },
+        {
+            "cdmc": "",
+            "kch": "B5020021",
+            "kcmc": "音乐鉴赏",
+            "ksmc": "2020-2021-1期末考试",
+            "kssj": "",
+            "row_id": "3",
+            "xnm": "2020",
+            "xqm": "3"
+        }
+    ],
+    "limit": 15,
+    "offset": 0,
+    "pageNo": 0,
+    "pageSize": 15,
+    "showCount": 5000,
+    "totalCount": 3,
+    "totalPage": 1,
+    "totalResult": 3
+}"#;
+
+    let parsed_exam_list = parse_exam_list_page(page).unwrap();
+    assert_eq!(parsed_exam_list.len(), 3);
+    assert_eq!(
+        parsed_exam_list[0].date(),
+        NaiveDate::from_ymd_opt(2021, 1, 12)
+    );
+    assert_eq!(
+        parsed_exam_list[0].start_time(),
+        NaiveTime::from_hms_opt(9, 30, 0)
+    );
+    assert_eq!(
+        parsed_exam_list[1].end_time(),
+        NaiveTime::from_hms_opt(15, 0, 0)
+    );
+    assert_eq!(parsed_exam_list[1].seat_no(), "5");
+    // The exam which is not scheduled yet is kept without time.
+    assert_eq!(parsed_exam_list[2].course(), "音乐鉴赏");
+    assert_eq!(parsed_exam_list[2].date(), None);
+    assert_eq!(parsed_exam_list[2].start_time(), None);
+}
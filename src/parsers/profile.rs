@@ -1,5 +1,4 @@
-use crate::parsers::ParserError;
-use crate::Result;
+use crate::error::{Result, ZfError};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -28,6 +27,20 @@ pub struct Profile {
     types: String,
 }
 
+impl Profile {
+    pub fn student_no(&self) -> &str {
+        &self.student_no
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn enrollment_date(&self) -> &str {
+        &self.enrollment_date
+    }
+}
+
 static ELEMENTS: [(&str, &str); 11] = [
     ("student_no", "#col_xh > p:nth-child(1)"),
     ("name", "#col_xm > p:nth-child(1)"),
@@ -48,13 +61,13 @@ pub fn parse_profile_page(text: &str) -> Result<Profile> {
     let pages = Html::parse_document(text);
     let mut values = Vec::new();
 
-    for (_, selector) in ELEMENTS {
+    for (name, selector) in ELEMENTS {
         let selectors = Selector::parse(selector).unwrap();
         let value = pages
             .select(&selectors)
             .next()
             .map(|x| x.inner_html().trim().to_string())
-            .ok_or(ParserError::MissingField)?;
+            .ok_or_else(|| ZfError::parse(name, "element not found"))?;
         values.push(value);
     }
     // It can be true that element.len() == ELEMENTS.len().
@@ -1,23 +1,212 @@
+use crate::gpa::{GpaCalculator, SitStandard};
 use crate::parsers::{get_f32, get_str, Semester};
 use crate::Result;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::fmt;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Score {
     /// 成绩
-    score: f32,
+    pub(crate) grade: Grade,
+    /// 绩点
+    pub(crate) grade_point: Option<f32>,
     /// 课程
-    course: String,
+    pub(crate) course: String,
     /// 课程代码
-    course_id: String,
+    pub(crate) course_id: String,
     /// 班级
-    class_id: String,
+    pub(crate) class_id: String,
     /// 学年
-    school_year: String,
+    pub(crate) school_year: String,
     /// 学期
-    semester: Semester,
+    pub(crate) semester: Semester,
     /// 学分
-    credit: f32,
+    pub(crate) credit: f32,
+    /// 课程性质, 如必修课、公选课
+    pub(crate) course_nature: String,
+    /// 考试性质
+    pub(crate) exam_nature: ExamNature,
+}
+
+/// 五级制成绩
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FiveLevel {
+    /// 优秀
+    Excellent,
+    /// 良好
+    Good,
+    /// 中等
+    Medium,
+    /// 及格
+    Pass,
+    /// 不及格
+    Fail,
+}
+
+/// 成绩, including the non-numeric ones
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Grade {
+    /// 百分制
+    Numeric(f32),
+    /// 五级制
+    FiveLevel(FiveLevel),
+    /// 二级制, 合格/不合格 or P/F
+    PassFail(bool),
+    /// 缺考
+    Absent,
+    /// 免修
+    Exempt,
+    /// 缓考
+    Deferred,
+    /// Grade that can not be recognized
+    Other(String),
+}
+
+/// 考试性质
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExamNature {
+    /// 正常考试
+    Normal,
+    /// 补考
+    MakeUp,
+    /// 重修
+    Retake,
+    Other(String),
+}
+
+impl Score {
+    pub fn course(&self) -> &str {
+        &self.course
+    }
+
+    pub fn course_id(&self) -> &str {
+        &self.course_id
+    }
+
+    pub fn grade(&self) -> &Grade {
+        &self.grade
+    }
+
+    pub fn grade_point(&self) -> Option<f32> {
+        self.grade_point
+    }
+
+    pub fn credit(&self) -> f32 {
+        self.credit
+    }
+
+    pub fn class_id(&self) -> &str {
+        &self.class_id
+    }
+
+    /// School year like "2020-2021"
+    pub fn school_year(&self) -> &str {
+        &self.school_year
+    }
+
+    pub fn semester(&self) -> &Semester {
+        &self.semester
+    }
+
+    pub fn course_nature(&self) -> &str {
+        &self.course_nature
+    }
+
+    pub fn exam_nature(&self) -> &ExamNature {
+        &self.exam_nature
+    }
+}
+
+impl Grade {
+    pub fn parse(grade: &str) -> Grade {
+        let grade = grade.trim();
+        if let Ok(score) = grade.parse::<f32>() {
+            return Grade::Numeric(score);
+        }
+        match grade {
+            "优秀" | "优" => Grade::FiveLevel(FiveLevel::Excellent),
+            "良好" | "良" => Grade::FiveLevel(FiveLevel::Good),
+            "中等" | "中" => Grade::FiveLevel(FiveLevel::Medium),
+            "及格" => Grade::FiveLevel(FiveLevel::Pass),
+            "不及格" => Grade::FiveLevel(FiveLevel::Fail),
+            "合格" | "通过" | "P" => Grade::PassFail(true),
+            "不合格" | "不通过" | "F" => Grade::PassFail(false),
+            "缺考" => Grade::Absent,
+            "免修" | "免考" => Grade::Exempt,
+            "缓考" => Grade::Deferred,
+            _ => Grade::Other(grade.to_string()),
+        }
+    }
+
+    /// Score in hundred-mark system, `None` for grades which can not be converted.
+    ///
+    /// Five-level grades are converted to the middle of their ranges, so that 不及格 is 30 in 0 to
+    /// 59. Absent is 0, as no mark is given.
+    pub fn to_percentage(&self) -> Option<f32> {
+        match self {
+            Grade::Numeric(score) => Some(*score),
+            Grade::FiveLevel(FiveLevel::Excellent) => Some(95.0),
+            Grade::FiveLevel(FiveLevel::Good) => Some(85.0),
+            Grade::FiveLevel(FiveLevel::Medium) => Some(75.0),
+            Grade::FiveLevel(FiveLevel::Pass) => Some(65.0),
+            Grade::FiveLevel(FiveLevel::Fail) => Some(30.0),
+            Grade::Absent => Some(0.0),
+            _ => None,
+        }
+    }
+
+    /// Whether the course is passed, `None` if not decided yet or can not be known.
+    pub fn is_passed(&self) -> Option<bool> {
+        match self {
+            Grade::PassFail(passed) => Some(*passed),
+            Grade::Exempt => Some(true),
+            Grade::Deferred | Grade::Other(_) => None,
+            _ => self.to_percentage().map(|score| score >= 60.0),
+        }
+    }
+}
+
+impl fmt::Display for Grade {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            Grade::Numeric(score) => return write!(f, "{}", score),
+            Grade::FiveLevel(FiveLevel::Excellent) => "优秀",
+            Grade::FiveLevel(FiveLevel::Good) => "良好",
+            Grade::FiveLevel(FiveLevel::Medium) => "中等",
+            Grade::FiveLevel(FiveLevel::Pass) => "及格",
+            Grade::FiveLevel(FiveLevel::Fail) => "不及格",
+            Grade::PassFail(true) => "合格",
+            Grade::PassFail(false) => "不合格",
+            Grade::Absent => "缺考",
+            Grade::Exempt => "免修",
+            Grade::Deferred => "缓考",
+            Grade::Other(grade) => grade,
+        };
+        f.write_str(text)
+    }
+}
+
+impl ExamNature {
+    pub fn parse(nature: &str) -> ExamNature {
+        match nature.trim() {
+            "" | "正常考试" => ExamNature::Normal,
+            x if x.contains("补考") => ExamNature::MakeUp,
+            x if x.contains("重修") => ExamNature::Retake,
+            x => ExamNature::Other(x.to_string()),
+        }
+    }
+}
+
+/// 成绩分项, 如平时成绩、期末成绩
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ScoreComponent {
+    /// 分项名称
+    name: String,
+    /// 成绩比例, 百分数
+    weight: Option<f32>,
+    /// 成绩
+    score: Grade,
 }
 
 pub fn parse_score_list_page(page: &str) -> Result<Vec<Score>> {
@@ -26,29 +215,176 @@ pub fn parse_score_list_page(page: &str) -> Result<Vec<Score>> {
     let result = json_page["items"].as_array().map(|course_list| {
         course_list
             .iter()
-            .map(|course| Score {
-                score: get_f32(course.get("cj")),
-                course: get_str(course.get("kcmc")),
-                course_id: get_str(course.get("kch")),
-                class_id: get_str(course.get("jxb_id")),
-                school_year: get_str(course.get("xnmmc")),
-                semester: Semester::from_raw(&get_str(course.get("xqm"))).unwrap(),
-                credit: get_f32(course.get("xf")),
+            .map(|course| {
+                Ok(Score {
+                    grade: Grade::parse(&get_str(course.get("cj"))),
+                    grade_point: get_str(course.get("jd")).parse().ok(),
+                    course: get_str(course.get("kcmc")),
+                    course_id: get_str(course.get("kch")),
+                    class_id: get_str(course.get("jxb_id")),
+                    school_year: get_str(course.get("xnmmc")),
+                    semester: Semester::from_raw(&get_str(course.get("xqm")))?,
+                    credit: get_f32(course, "xf")?,
+                    course_nature: get_str(course.get("kcxzmc")),
+                    exam_nature: ExamNature::parse(&get_str(course.get("ksxz"))),
+                })
             })
             .collect()
     });
-    match result {
-        Some(v) => Ok(v),
-        None => Ok(vec![]),
-    }
+    result.unwrap_or_else(|| Ok(vec![]))
 }
 
+/// GPA in SIT standard, 0 if no course is counted. See `gpa::GpaCalculator` for other strategies.
 pub fn calculate_gpa(scores: Vec<Score>) -> f32 {
-    let mut total_credits = 0.0;
-    let mut t = 0.0;
-    for s in scores {
-        t += s.credit * s.score;
-        total_credits += s.credit;
+    GpaCalculator::new(SitStandard)
+        .calculate(&scores)
+        .cumulative
+        .unwrap_or_default()
+}
+
+impl ScoreComponent {
+    pub fn name(&self) -> &str {
+        &self.name
     }
-    (t / total_credits / 10.0) - 5.0
+
+    /// Weight in percent, `None` for the total score
+    pub fn weight(&self) -> Option<f32> {
+        self.weight
+    }
+
+    pub fn score(&self) -> &Grade {
+        &self.score
+    }
+}
+
+pub fn parse_score_detail_page(page: &str) -> Result<Vec<ScoreComponent>> {
+    use scraper::{Html, Selector};
+
+    let document = Html::parse_document(page);
+    let row_selector = Selector::parse("table#subtab tbody tr").unwrap();
+    let cell_selector = Selector::parse("td").unwrap();
+
+    let clean = |s: String| s.replace('\u{a0}', "").trim().to_string();
+    let mut result = Vec::new();
+    for row in document.select(&row_selector) {
+        let cells: Vec<String> = row
+            .select(&cell_selector)
+            .map(|cell| clean(cell.text().collect()))
+            .collect();
+        if cells.len() < 3 {
+            continue;
+        }
+        let name = cells[0]
+            .trim_matches(|c: char| c == '【' || c == '】' || c.is_whitespace())
+            .to_string();
+        result.push(ScoreComponent {
+            name,
+            weight: cells[1].trim_end_matches('%').trim().parse().ok(),
+            score: Grade::parse(&cells[2]),
+        });
+    }
+    Ok(result)
+}
+
+#[test]
+fn test_parse_score_detail_page() {
+    let page = r#"
+<table class="table table-bordered table-striped table-hover tab-bor-col-1 tab-td-padding-5" id="subtab">
+    <thead>
+        <tr><th>成绩分项</th><th>成绩比例</th><th>成绩</th></tr>
+    </thead>
+    <tbody>
+        <tr><td valign="middle">【 平时 】</td><td valign="middle">40%&nbsp;</td><td valign="middle">92&nbsp;</td></tr>
+        <tr><td valign="middle">【 期末 】</td><td valign="middle">60%&nbsp;</td><td valign="middle">81&nbsp;</td></tr>
+        <tr><td valign="middle">【 总评 】</td><td valign="middle">&nbsp;</td><td valign="middle">86&nbsp;</td></tr>
+    </tbody>
+</table>"#;
+
+    let components = parse_score_detail_page(page).unwrap();
+    assert_eq!(components.len(), 3);
+    assert_eq!(components[0].name(), "平时");
+    assert_eq!(components[0].weight(), Some(40.0));
+    assert_eq!(components[1].score(), &Grade::Numeric(81.0));
+    assert_eq!(components[2].weight(), None);
+}
+
+#[test]
+fn test_parse_score_list_page() {
+    let page = r#"
+{
+    "items": [
+        {
+            "cj": "86",
+            "jd": "3.60",
+            "jxb_id": "B5C1A8C1E0E5D1A0E0530100007F7C6E",
+            "kch": "B1020011",
+            "kcmc": "大学物理A1",
+            "kcxzmc": "必修课",
+            "ksxz": "正常考试",
+            "xf": "4.0",
+            "xnmmc": "2020-2021",
+            "xqm": "3"
+        },
+        {
+            "cj": "优秀",
+            "jd": "4.50",
+            "jxb_id": "B5C1A8C1E0E5D1A0E0530100007F7C6F",
+            "kch": "B0000001",
+            "kcmc": "形势与政策",
+            "kcxzmc": "必修课",
+            "ksxz": "正常考试",
+            "xf": "0.5",
+            "xnmmc": "2020-2021",
+            "xqm": "3"
+        },
+        {
+            "cj": "合格",
+            "jxb_id": "B5C1A8C1E0E5D1A0E0530100007F7C70",
+            "kch": "G0000002",
+            "kcmc": "音乐鉴赏",
+            "kcxzmc": "公选课",
+            "ksxz": "重修",
+            "xf": "2.0",
+            "xnmmc": "2020-2021",
+            "xqm": "12"
+        },
+        {
+            "cj": "缺考",
+            "jd": "0",
+            "jxb_id": "B5C1A8C1E0E5D1A0E0530100007F7C71",
+            "kch": "B2041007",
+            "kcmc": "数据结构",
+            "kcxzmc": "必修课",
+            "ksxz": "补考一",
+            "xf": "3.0",
+            "xnmmc": "2020-2021",
+            "xqm": "12"
+        }
+    ]
+}"#;
+
+    let scores = parse_score_list_page(page).unwrap();
+    let grades: Vec<Grade> = scores.iter().map(|s| s.grade().clone()).collect();
+    assert_eq!(
+        grades,
+        vec![
+            Grade::Numeric(86.0),
+            Grade::FiveLevel(FiveLevel::Excellent),
+            Grade::PassFail(true),
+            Grade::Absent
+        ]
+    );
+    assert_eq!(scores[0].grade_point(), Some(3.6));
+    assert_eq!(scores[0].class_id(), "B5C1A8C1E0E5D1A0E0530100007F7C6E");
+    assert_eq!(scores[0].school_year(), "2020-2021");
+    assert_eq!(scores[0].semester(), &Semester::FirstTerm);
+    assert_eq!(scores[2].grade_point(), None);
+    assert_eq!(scores[2].course_nature(), "公选课");
+    assert_eq!(scores[2].exam_nature(), &ExamNature::Retake);
+    assert_eq!(scores[3].exam_nature(), &ExamNature::MakeUp);
+    assert_eq!(Grade::parse("不及格").to_percentage(), Some(30.0));
+    assert_eq!(Grade::parse("不及格").is_passed(), Some(false));
+    assert_eq!(Grade::parse("缺考").to_percentage(), Some(0.0));
+    assert_eq!(Grade::parse("免修").is_passed(), Some(true));
+    assert_eq!(Grade::parse("缓考").is_passed(), None);
 }
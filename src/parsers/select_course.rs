@@ -1,6 +1,14 @@
+use crate::parsers::get_str;
 use crate::Result;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::HashMap;
+
+lazy_static::lazy_static! {
+    static ref QUERY_COURSE_REGEX: Regex =
+        Regex::new(r"queryCourse\(this,\s*'(\w*)',\s*'(\w*)'").unwrap();
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SelectCourse {
@@ -13,6 +21,9 @@ pub struct SelectCourse {
     #[serde(rename(deserialize = "kch"))]
     /// 课程代码
     course_id: String,
+    #[serde(default, rename(deserialize = "kch_id"))]
+    /// 课程代码(内部表示)
+    pub(crate) inner_course_id: String,
     #[serde(rename(deserialize = "kklxdm"))]
     /// 开课学院
     college: String,
@@ -27,15 +38,400 @@ pub struct SelectCourse {
     dyn_class_id: String,
 }
 
+/// 选课类别, 如主修课程、通识选修课
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CourseType {
+    /// 类别名称
+    name: String,
+    /// 开课类型代码
+    pub(crate) type_id: String,
+    /// 选课控制代码, 标识当前轮次
+    pub(crate) round_id: String,
+}
+
+/// 当前选课轮次的参数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SelectionRound {
+    /// 选课是否开放
+    open: bool,
+    /// 可选的课程类别
+    course_types: Vec<CourseType>,
+    /// 页面中隐藏的表单参数, 后续请求需要原样提交
+    pub(crate) params: HashMap<String, String>,
+}
+
+/// 可选的教学班
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TeachingClass {
+    #[serde(rename(deserialize = "jxb_id"))]
+    /// 教学班(内部表示)
+    class_id: String,
+    #[serde(rename(deserialize = "do_jxb_id"))]
+    /// 选课时提交的教学班代码
+    pub(crate) select_id: String,
+    #[serde(default, rename(deserialize = "sksj"))]
+    /// 上课时间
+    time: String,
+    #[serde(default, rename(deserialize = "jxdd"))]
+    /// 上课地点
+    place: String,
+    #[serde(default, rename(deserialize = "jsxx"))]
+    /// 教师信息
+    teacher: String,
+    #[serde(default, rename(deserialize = "jxbrl"))]
+    /// 教学班容量
+    capacity: String,
+}
+
+/// 已选的教学班
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SelectedClass {
+    #[serde(rename(deserialize = "kcmc"))]
+    /// 课程名称
+    course_name: String,
+    #[serde(rename(deserialize = "kch_id"))]
+    /// 课程代码(内部表示)
+    pub(crate) inner_course_id: String,
+    #[serde(rename(deserialize = "jxbmc"))]
+    /// 教学班
+    dyn_class_id: String,
+    #[serde(rename(deserialize = "do_jxb_id"))]
+    /// 选课时提交的教学班代码
+    pub(crate) select_id: String,
+    #[serde(default, rename(deserialize = "sksj"))]
+    /// 上课时间
+    time: String,
+    #[serde(default, rename(deserialize = "jxdd"))]
+    /// 上课地点
+    place: String,
+    #[serde(default, rename(deserialize = "jsxx"))]
+    /// 教师信息
+    teacher: String,
+}
+
+/// 选课或退课的结果
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SelectionResult {
+    /// 成功
+    Success,
+    /// 教学班容量已满
+    Full,
+    /// 上课时间冲突
+    TimeConflict,
+    /// 当前不在选课时间
+    RoundClosed,
+    /// 其他错误, 附带服务器返回的消息
+    Failed(String),
+}
+
+impl SelectCourse {
+    pub fn course_name(&self) -> &str {
+        &self.course_name
+    }
+
+    pub fn sub_course_name(&self) -> &str {
+        &self.sub_course_name
+    }
+
+    pub fn course_id(&self) -> &str {
+        &self.course_id
+    }
+
+    pub fn college(&self) -> &str {
+        &self.college
+    }
+
+    pub fn total_size(&self) -> &str {
+        &self.total_size
+    }
+
+    pub fn dyn_class_id(&self) -> &str {
+        &self.dyn_class_id
+    }
+}
+
+impl TeachingClass {
+    pub fn class_id(&self) -> &str {
+        &self.class_id
+    }
+
+    pub fn time(&self) -> &str {
+        &self.time
+    }
+
+    pub fn place(&self) -> &str {
+        &self.place
+    }
+
+    pub fn teacher(&self) -> &str {
+        &self.teacher
+    }
+
+    pub fn capacity(&self) -> &str {
+        &self.capacity
+    }
+}
+
+impl SelectedClass {
+    pub fn course_name(&self) -> &str {
+        &self.course_name
+    }
+
+    pub fn dyn_class_id(&self) -> &str {
+        &self.dyn_class_id
+    }
+
+    pub fn time(&self) -> &str {
+        &self.time
+    }
+
+    pub fn place(&self) -> &str {
+        &self.place
+    }
+
+    pub fn teacher(&self) -> &str {
+        &self.teacher
+    }
+}
+
+impl SelectionRound {
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn course_types(&self) -> &[CourseType] {
+        &self.course_types
+    }
+}
+
+impl CourseType {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl SelectionResult {
+    fn from_message(message: &str) -> SelectionResult {
+        if message.contains("已满") {
+            SelectionResult::Full
+        } else if message.contains("冲突") {
+            SelectionResult::TimeConflict
+        } else if message.contains("选课时间") || message.contains("选课阶段") {
+            SelectionResult::RoundClosed
+        } else {
+            SelectionResult::Failed(message.to_string())
+        }
+    }
+}
+
+fn parse_json_list<T: serde::de::DeserializeOwned>(list: &Value) -> Result<Vec<T>> {
+    match list.as_array() {
+        Some(list) => Ok(list
+            .iter()
+            .map(|v| serde_json::from_value::<T>(v.clone()))
+            .collect::<serde_json::Result<Vec<T>>>()?),
+        None => Ok(vec![]),
+    }
+}
+
 pub fn parse_available_course_page(page: &str) -> Result<Vec<SelectCourse>> {
     let json_page: Value = serde_json::from_str(page)?;
 
-    if let Some(major_list) = json_page.as_array() {
-        let result = major_list
-            .iter()
-            .map(|v| serde_json::from_value::<SelectCourse>(v.clone()).unwrap())
-            .collect();
-        return Ok(result);
+    // The selection page wraps the course list in `tmpList`.
+    if json_page.is_object() {
+        return parse_json_list(&json_page["tmpList"]);
     }
-    Ok(vec![])
+    parse_json_list(&json_page)
+}
+
+pub fn parse_selection_round_page(page: &str) -> Result<SelectionRound> {
+    use scraper::{Html, Selector};
+
+    let document = Html::parse_document(page);
+    let mut params = HashMap::new();
+    for input in document.select(&Selector::parse("input[type=hidden]").unwrap()) {
+        let element = input.value();
+        if let Some(name) = element.attr("name").or_else(|| element.attr("id")) {
+            params.insert(
+                name.to_string(),
+                element.attr("value").unwrap_or_default().to_string(),
+            );
+        }
+    }
+
+    let course_types: Vec<CourseType> = document
+        .select(&Selector::parse("a[onclick]").unwrap())
+        .filter_map(|tab| {
+            let captures = QUERY_COURSE_REGEX.captures(tab.value().attr("onclick")?)?;
+            Some(CourseType {
+                name: tab.text().collect::<String>().trim().to_string(),
+                type_id: captures[1].to_string(),
+                round_id: captures[2].to_string(),
+            })
+        })
+        .collect();
+
+    Ok(SelectionRound {
+        open: !course_types.is_empty(),
+        course_types,
+        params,
+    })
+}
+
+pub fn parse_teaching_class_page(page: &str) -> Result<Vec<TeachingClass>> {
+    let json_page: Value = serde_json::from_str(page)?;
+    parse_json_list(&json_page)
+}
+
+pub fn parse_selected_class_page(page: &str) -> Result<Vec<SelectedClass>> {
+    let json_page: Value = serde_json::from_str(page)?;
+    parse_json_list(&json_page)
+}
+
+/// Parse the response of selecting a class, like `{"flag":"1"}` or `{"flag":"-1","msg":"..."}`.
+///
+/// Flag `-1` is used for full classes, time conflicts, credit limits and closed rounds alike, so
+/// the result is told by the message.
+pub fn parse_select_result(page: &str) -> Result<SelectionResult> {
+    let json_page: Value = serde_json::from_str(page)?;
+    let result = match get_str(json_page.get("flag")).as_str() {
+        "1" => SelectionResult::Success,
+        _ => SelectionResult::from_message(&get_str(json_page.get("msg"))),
+    };
+    Ok(result)
+}
+
+/// Parse the response of dropping a class, which is `"1"` on success or an error message.
+pub fn parse_drop_result(page: &str) -> Result<SelectionResult> {
+    let message = match serde_json::from_str::<Value>(page) {
+        Ok(Value::String(s)) => s,
+        Ok(v) => v.to_string(),
+        Err(_) => page.trim().to_string(),
+    };
+    if message == "1" {
+        return Ok(SelectionResult::Success);
+    }
+    Ok(SelectionResult::from_message(&message))
+}
+
+#[test]
+fn test_parse_selection_round_page() {
+    let page = r#"
+<html>
+<body>
+<input type="hidden" name="xkxnm" id="xkxnm" value="2021"/>
+<input type="hidden" name="xkxqm" id="xkxqm" value="3"/>
+<input type="hidden" name="njdm_id" id="njdm_id" value="2019"/>
+<input type="hidden" name="zyh_id" id="zyh_id" value="B0401"/>
+<input type="hidden" name="xqh_id" id="xqh_id" value="02"/>
+<ul class="nav nav-tabs sl_nav_tabs" role="tablist" id="nav_tab">
+    <li><a href="javascript:void(0);" onclick="queryCourse(this,'10','A8A8B1A36B8D5AE1E0530100007F8E46','2019','B0401')" role="tab" data-toggle="tab">主修课程</a></li>
+    <li><a href="javascript:void(0);" onclick="queryCourse(this,'05','A8A8B1A36B915AE1E0530100007F8E46','2019','B0401')" role="tab" data-toggle="tab">通识选修课</a></li>
+</ul>
+</body>
+</html>"#;
+
+    let round = parse_selection_round_page(page).unwrap();
+    println!("{:#?}", round);
+    assert!(round.is_open());
+    assert_eq!(round.course_types().len(), 2);
+    assert_eq!(round.course_types()[1].name(), "通识选修课");
+    assert_eq!(round.course_types()[1].type_id, "05");
+    assert_eq!(round.params["xkxnm"], "2021");
+
+    let closed = parse_selection_round_page(
+        r#"<html><body><div class="nodata"><span>对不起，当前不属于选课阶段，如有需要，请与管理员联系！</span></div></body></html>"#,
+    )
+    .unwrap();
+    assert!(!closed.is_open());
+}
+
+#[test]
+fn test_parse_available_course_page() {
+    let page = r#"
+{
+    "sfxsjc": "1",
+    "tmpList": [
+        {
+            "blyxrs": "0",
+            "blzyl": "0",
+            "cxbj": "0",
+            "fxbj": "0",
+            "jxb_id": "C0B1B6F6DBA2A1B6E0530100007F2B84",
+            "jxbmc": "(2021-2022-1)-B1020011-01",
+            "jxbzls": "1",
+            "kch": "B1020011",
+            "kch_id": "B1020011",
+            "kcmc": "大学物理A1",
+            "kcrow": "1",
+            "kklxdm": "10",
+            "kzmc": "大学物理A1",
+            "xf": "4.0",
+            "yxzrs": "102"
+        }
+    ]
+}"#;
+
+    let course_list = parse_available_course_page(page).unwrap();
+    println!("{:#?}", course_list);
+    assert_eq!(course_list.len(), 1);
+    assert_eq!(course_list[0].inner_course_id, "B1020011");
+    assert_eq!(course_list[0].dyn_class_id(), "(2021-2022-1)-B1020011-01");
+}
+
+#[test]
+fn test_parse_teaching_class_page() {
+    let page = r#"
+[
+    {
+        "do_jxb_id": "6ab6c6a2a5b4e1c4b4d7c8e0f1a2b3c4",
+        "jsxx": "1001/张三/教授",
+        "jxb_id": "C0B1B6F6DBA2A1B6E0530100007F2B84",
+        "jxbrl": "120",
+        "jxdd": "一教A204",
+        "sksj": "星期一第1-2节{1-16周}"
+    }
+]"#;
+
+    let class_list = parse_teaching_class_page(page).unwrap();
+    assert_eq!(class_list.len(), 1);
+    assert_eq!(class_list[0].teacher(), "1001/张三/教授");
+    assert_eq!(class_list[0].time(), "星期一第1-2节{1-16周}");
+    assert_eq!(class_list[0].place(), "一教A204");
+    assert_eq!(class_list[0].capacity(), "120");
+}
+
+#[test]
+fn test_parse_selection_result() {
+    let results = [
+        (r#"{"flag":"1"}"#, SelectionResult::Success),
+        (
+            r#"{"flag":"-1","msg":"所选教学班的容量已满，无法选修！"}"#,
+            SelectionResult::Full,
+        ),
+        (
+            r#"{"flag":"0","msg":"所选教学班的上课时间与其他教学班有冲突！"}"#,
+            SelectionResult::TimeConflict,
+        ),
+        (
+            r#"{"flag":"0","msg":"对不起，当前不属于选课时间范围！"}"#,
+            SelectionResult::RoundClosed,
+        ),
+        (
+            r#"{"flag":"-1","msg":"超过本学期最高选课学分要求！"}"#,
+            SelectionResult::Failed("超过本学期最高选课学分要求！".to_string()),
+        ),
+    ];
+    for (page, expected) in results {
+        assert_eq!(parse_select_result(page).unwrap(), expected);
+    }
+
+    assert_eq!(
+        parse_drop_result(r#""1""#).unwrap(),
+        SelectionResult::Success
+    );
+    assert_eq!(
+        parse_drop_result("退课失败！").unwrap(),
+        SelectionResult::Failed("退课失败！".to_string())
+    );
 }
@@ -1,113 +1,270 @@
 use crate::parsers::{get_f32, get_str};
+use crate::schedule::PeriodSchedule;
 use crate::Result;
+use chrono::{NaiveDate, NaiveTime};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 lazy_static::lazy_static! {
-    static ref WEEK_REGEX: Regex = Regex::new(r"(\d{1,2})(:?-(\d{1,2}))?").unwrap();
+    static ref WEEK_REGEX: Regex = Regex::new(r"(\d{1,2})(?:-(\d{1,2}))?周?(?:\((单|双)\))?").unwrap();
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Course {
     /// 课程名称
-    pub(crate) course_name: String,
-    /// 星期
-    day: i32,
+    pub course_name: String,
+    /// 星期, `None` if the course has no fixed time
+    pub day: Option<Weekday>,
     /// 节次
-    time_index: Vec<String>,
+    pub periods: Option<PeriodRange>,
     /// 周次
-    weeks: Vec<String>,
+    pub weeks: WeekSet,
     /// 教室
-    place: String,
+    pub place: String,
     /// 教师
-    teacher: Vec<String>,
+    pub teachers: Vec<String>,
     /// 校区
-    campus: String,
+    pub campus: String,
     /// 学分
-    credit: f32,
+    pub credit: f32,
     /// 学时
-    hours: f32,
+    pub hours: f32,
     /// 教学班
-    dyn_class_id: String,
+    pub dyn_class_id: String,
     /// 课程代码
-    course_id: String,
+    pub course_id: String,
     /// 陪课班
-    prefered_class: Vec<String>,
+    pub preferred_classes: Vec<String>,
 }
 
-fn trans_week(week_day: &str) -> i32 {
-    match week_day {
-        "星期一" => 1,
-        "星期二" => 2,
-        "星期三" => 3,
-        "星期四" => 4,
-        "星期五" => 5,
-        "星期六" => 6,
-        "星期日" => 7,
-        _ => 0,
+/// 星期
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Weekday {
+    Monday = 1,
+    Tuesday = 2,
+    Wednesday = 3,
+    Thursday = 4,
+    Friday = 5,
+    Saturday = 6,
+    Sunday = 7,
+}
+
+/// Set of week numbers (周次) in a term, stored as a bitset.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "Vec<u32>", into = "Vec<u32>")]
+pub struct WeekSet(u64);
+
+/// Continuous periods (节次) of a course, both ends inclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PeriodRange {
+    pub start: i32,
+    pub end: i32,
+}
+
+impl Course {
+    /// Start and end time of the course on the given date, from its first and last period.
+    pub fn time_range(
+        &self,
+        schedule: &PeriodSchedule,
+        date: NaiveDate,
+    ) -> Option<(NaiveTime, NaiveTime)> {
+        let periods = self.periods?;
+        let first = schedule.period(&self.campus, date, periods.start)?;
+        let last = schedule.period(&self.campus, date, periods.end)?;
+        Some((first.start, last.end))
+    }
+
+    pub fn start_time(&self, schedule: &PeriodSchedule, date: NaiveDate) -> Option<NaiveTime> {
+        self.time_range(schedule, date).map(|(start, _)| start)
+    }
+
+    pub fn end_time(&self, schedule: &PeriodSchedule, date: NaiveDate) -> Option<NaiveTime> {
+        self.time_range(schedule, date).map(|(_, end)| end)
     }
 }
 
-pub fn expand_weeks_str(week_string: &str) -> Vec<String> {
-    let check_time_index = |x: &str| -> i32 {
-        if let Ok(x) = x.parse() {
-            return x;
+impl Weekday {
+    /// Parse weekday name like "星期一"
+    pub fn from_chinese(name: &str) -> Option<Weekday> {
+        match name {
+            "星期一" => Some(Weekday::Monday),
+            "星期二" => Some(Weekday::Tuesday),
+            "星期三" => Some(Weekday::Wednesday),
+            "星期四" => Some(Weekday::Thursday),
+            "星期五" => Some(Weekday::Friday),
+            "星期六" => Some(Weekday::Saturday),
+            "星期日" | "星期天" => Some(Weekday::Sunday),
+            _ => None,
         }
-        0
-    };
+    }
 
-    let transform_number = |x: i32| -> String { x.to_string() };
+    /// Day number in week, Monday is 1 and Sunday is 7.
+    pub fn number(&self) -> u32 {
+        *self as u32
+    }
 
-    let mut weeks = Vec::new();
-    week_string.split(',').for_each(|week_string| {
-        if week_string.contains('-') {
-            let mut step = 1;
-            if week_string.ends_with("(单)") || week_string.ends_with("(双)") {
-                step = 2;
-            }
-            let range = WEEK_REGEX.captures(week_string).unwrap();
-            let mut min = check_time_index(range.get(1).unwrap().as_str());
-            let max = check_time_index(range.get(3).unwrap().as_str());
-            while min < max + 1 {
-                weeks.push(transform_number(min));
-                min += step;
-            }
-        } else {
-            weeks.push(week_string.replace("周", ""));
-        }
-    });
+    pub fn from_number(number: u32) -> Option<Weekday> {
+        Self::all().get((number as usize).checked_sub(1)?).copied()
+    }
 
-    weeks
+    pub fn all() -> [Weekday; 7] {
+        [
+            Weekday::Monday,
+            Weekday::Tuesday,
+            Weekday::Wednesday,
+            Weekday::Thursday,
+            Weekday::Friday,
+            Weekday::Saturday,
+            Weekday::Sunday,
+        ]
+    }
 }
 
-pub fn expand_time_index(time_string: &str) -> Vec<String> {
-    let check_time_index = |x: &str| -> i32 {
-        if let Ok(x) = x.parse() {
-            return x;
+impl From<Weekday> for chrono::Weekday {
+    fn from(day: Weekday) -> Self {
+        match day {
+            Weekday::Monday => chrono::Weekday::Mon,
+            Weekday::Tuesday => chrono::Weekday::Tue,
+            Weekday::Wednesday => chrono::Weekday::Wed,
+            Weekday::Thursday => chrono::Weekday::Thu,
+            Weekday::Friday => chrono::Weekday::Fri,
+            Weekday::Saturday => chrono::Weekday::Sat,
+            Weekday::Sunday => chrono::Weekday::Sun,
         }
-        0
-    };
-    let transform_number = |x: i32| -> String { x.to_string() };
-
-    let mut indices = Vec::new();
-    if time_string.contains('-') {
-        if let Some((min, max)) = time_string.split_once('-') {
-            let (range_left, range_right) = (check_time_index(min), check_time_index(max));
-            let ranges = range_left..(range_right + 1);
-            for range in ranges {
-                indices.push(transform_number(range));
+    }
+}
+
+impl WeekSet {
+    /// The largest week number that can be stored
+    pub const MAX_WEEK: u32 = 63;
+
+    pub fn new() -> Self {
+        WeekSet(0)
+    }
+
+    /// Parse week string like "1-15周(单),16周"
+    pub fn parse(week_string: &str) -> WeekSet {
+        let mut weeks = WeekSet::new();
+        for range in WEEK_REGEX.captures_iter(week_string) {
+            let (min, max) = match (range[1].parse::<u32>(), range.get(2)) {
+                (Ok(min), Some(max)) => (min, max.as_str().parse().unwrap_or(min)),
+                (Ok(min), None) => (min, min),
+                _ => continue,
+            };
+            let parity = range.get(3).map(|x| x.as_str());
+            for week in min..=max {
+                match parity {
+                    Some("单") if week % 2 == 0 => continue,
+                    Some("双") if week % 2 == 1 => continue,
+                    _ => weeks.insert(week),
+                }
             }
         }
-    } else {
-        indices.push(String::from(time_string));
+        weeks
+    }
+
+    pub fn insert(&mut self, week: u32) {
+        if (1..=Self::MAX_WEEK).contains(&week) {
+            self.0 |= 1 << week;
+        }
+    }
+
+    pub fn contains(&self, week: u32) -> bool {
+        (1..=Self::MAX_WEEK).contains(&week) && self.0 & (1 << week) != 0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = u32> + '_ {
+        (1..=Self::MAX_WEEK).filter(move |week| self.contains(*week))
+    }
+
+    /// Odd weeks (单周) in the set
+    pub fn odd(&self) -> WeekSet {
+        WeekSet(self.0 & 0xAAAA_AAAA_AAAA_AAAA)
+    }
+
+    /// Even weeks (双周) in the set
+    pub fn even(&self) -> WeekSet {
+        WeekSet(self.0 & 0x5555_5555_5555_5554)
+    }
+
+    /// Whether all weeks are odd, namely "单周" course
+    pub fn is_odd_only(&self) -> bool {
+        !self.is_empty() && self.even().is_empty()
+    }
+
+    /// Whether all weeks are even, namely "双周" course
+    pub fn is_even_only(&self) -> bool {
+        !self.is_empty() && self.odd().is_empty()
+    }
+
+    pub fn intersection(&self, other: &WeekSet) -> WeekSet {
+        WeekSet(self.0 & other.0)
+    }
+
+    pub fn union(&self, other: &WeekSet) -> WeekSet {
+        WeekSet(self.0 | other.0)
+    }
+}
+
+impl From<WeekSet> for Vec<u32> {
+    fn from(weeks: WeekSet) -> Self {
+        weeks.iter().collect()
     }
-    indices
 }
 
-fn split_string(s: String) -> Vec<String> {
-    let result: Vec<String> = s.split(',').map(ToString::to_string).collect();
-    result
+impl From<Vec<u32>> for WeekSet {
+    fn from(weeks: Vec<u32>) -> Self {
+        let mut set = WeekSet::new();
+        weeks.into_iter().for_each(|week| set.insert(week));
+        set
+    }
+}
+
+impl PeriodRange {
+    /// Parse period string like "1-2" or "3"
+    pub fn parse(period_string: &str) -> Option<PeriodRange> {
+        let (start, end) = match period_string.split_once('-') {
+            Some((start, end)) => (start.trim().parse().ok()?, end.trim().parse().ok()?),
+            None => {
+                let index = period_string.trim().parse().ok()?;
+                (index, index)
+            }
+        };
+        Some(PeriodRange {
+            start: std::cmp::min(start, end),
+            end: std::cmp::max(start, end),
+        })
+    }
+
+    pub fn contains(&self, index: i32) -> bool {
+        self.start <= index && index <= self.end
+    }
+
+    pub fn overlaps(&self, other: &PeriodRange) -> bool {
+        self.start <= other.end && other.start <= self.end
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = i32> {
+        self.start..=self.end
+    }
+}
+
+/// Split names like "张三,李四" or "张三、李四"
+fn split_names(s: &str) -> Vec<String> {
+    s.split([',', '，', '、', ';'])
+        .map(str::trim)
+        .filter(|x| !x.is_empty())
+        .map(ToString::to_string)
+        .collect()
 }
 
 pub fn parse_timetable_page(page: &str) -> Result<Vec<Course>> {
@@ -118,20 +275,53 @@ pub fn parse_timetable_page(page: &str) -> Result<Vec<Course>> {
         for each_course in course {
             result.push(Course {
                 course_name: get_str(each_course.get("kcmc")),
-                day: trans_week(get_str(each_course.get("xqjmc")).as_str()),
-                time_index: expand_time_index(get_str(each_course.get("jcs")).as_str()),
-                weeks: expand_weeks_str(get_str(each_course.get("zcd")).as_str()),
+                day: Weekday::from_chinese(get_str(each_course.get("xqjmc")).as_str()),
+                periods: PeriodRange::parse(get_str(each_course.get("jcs")).as_str()),
+                weeks: WeekSet::parse(get_str(each_course.get("zcd")).as_str()),
                 place: get_str(each_course.get("cdmc")),
-                teacher: split_string(get_str(each_course.get("xm"))),
+                teachers: split_names(&get_str(each_course.get("xm"))),
                 campus: get_str(each_course.get("xqmc")),
-                credit: get_f32(each_course.get("xf")),
-                hours: get_f32(each_course.get("zxs")),
+                credit: get_f32(each_course, "xf")?,
+                hours: get_f32(each_course, "zxs")?,
                 dyn_class_id: get_str(each_course.get("jxbmc")),
                 course_id: get_str(each_course.get("kch")),
-                prefered_class: split_string(get_str(each_course.get("jxbzc"))),
+                preferred_classes: split_names(&get_str(each_course.get("jxbzc"))),
             })
         }
         return Ok(result);
     }
     Ok(vec![])
 }
+
+#[test]
+fn test_parse_course_fields() {
+    let weeks = WeekSet::parse("1-7周(单),8周,10-16周(双)");
+    assert_eq!(Vec::<u32>::from(weeks), vec![1, 3, 5, 7, 8, 10, 12, 14, 16]);
+    assert!(WeekSet::parse("2-16周(双)").is_even_only());
+    assert!(WeekSet::parse("1-15周(单)").is_odd_only());
+    assert!(!weeks.is_odd_only());
+    assert_eq!(weeks.odd().len(), 4);
+    assert_eq!(WeekSet::parse("1-16周").len(), 16);
+
+    assert_eq!(
+        PeriodRange::parse("3-4"),
+        Some(PeriodRange { start: 3, end: 4 })
+    );
+    assert_eq!(
+        PeriodRange::parse("5"),
+        Some(PeriodRange { start: 5, end: 5 })
+    );
+    assert_eq!(PeriodRange::parse(""), None);
+
+    assert_eq!(Weekday::from_chinese("星期三"), Some(Weekday::Wednesday));
+    assert_eq!(Weekday::from_number(7), Some(Weekday::Sunday));
+    assert_eq!(Weekday::from_number(0), None);
+    assert_eq!(
+        split_names("张三, 李四、王五"),
+        vec!["张三", "李四", "王五"]
+    );
+
+    let json = serde_json::to_string(&weeks).unwrap();
+    assert_eq!(json, "[1,3,5,7,8,10,12,14,16]");
+    assert_eq!(serde_json::from_str::<WeekSet>(&json).unwrap(), weeks);
+}
@@ -0,0 +1,172 @@
+//! Retry and rate limit policy of the requests of a session, see `SessionBuilder::retry` and
+//! `SessionBuilder::rate_limit`.
+
+use rand::Rng;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Retry with exponential backoff and jitter, on transport errors and 5xx responses.
+///
+/// Only idempotent requests are retried, namely GET requests and the queries of the client.
+/// Course selection and the login form are never retried, so that they are not submitted twice.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    max_retries: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    /// 3 retries, starting from 500ms and up to 10s.
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(10),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn new(max_retries: u32) -> Self {
+        RetryPolicy {
+            max_retries,
+            ..RetryPolicy::default()
+        }
+    }
+
+    /// Never retry.
+    pub fn none() -> Self {
+        RetryPolicy::new(0)
+    }
+
+    /// Delay before the first retry, doubled for each of the next ones.
+    pub fn base_delay(mut self, base_delay: Duration) -> Self {
+        self.base_delay = base_delay;
+        self
+    }
+
+    /// Upper limit of the delay.
+    pub fn max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    pub(crate) fn max_retries(&self) -> u32 {
+        self.max_retries
+    }
+
+    /// Delay before the retry, `retry` counts from 0. A random half of the delay is cut off, so
+    /// that concurrent requests do not retry at the same time.
+    pub(crate) fn delay(&self, retry: u32) -> Duration {
+        let delay = self
+            .base_delay
+            .checked_mul(1 << std::cmp::min(retry, 16))
+            .map_or(self.max_delay, |delay| std::cmp::min(delay, self.max_delay));
+        delay.mul_f64(rand::thread_rng().gen_range(0.5..=1.0))
+    }
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Token bucket rate limiter. Clones share the bucket, so one limiter can be set on several
+/// sessions to limit them together.
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    /// Tokens added per second
+    rate: f64,
+    burst: f64,
+    bucket: Arc<Mutex<Bucket>>,
+}
+
+impl RateLimiter {
+    /// Allow `rate` requests per second on average, and up to `burst` requests at once.
+    pub fn new(rate: f64, burst: u32) -> Self {
+        let burst = f64::from(std::cmp::max(burst, 1));
+        RateLimiter {
+            rate: rate.max(f64::MIN_POSITIVE),
+            burst,
+            bucket: Arc::new(Mutex::new(Bucket {
+                tokens: burst,
+                updated: Instant::now(),
+            })),
+        }
+    }
+
+    /// Wait until a request is allowed.
+    pub async fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut bucket = self
+                    .bucket
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner());
+                let now = Instant::now();
+                let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+                bucket.tokens = (bucket.tokens + elapsed * self.rate).min(self.burst);
+                bucket.updated = now;
+                if bucket.tokens >= 1.0 {
+                    bucket.tokens -= 1.0;
+                    return;
+                }
+                Duration::from_secs_f64((1.0 - bucket.tokens) / self.rate)
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+#[test]
+fn test_retry_delay() {
+    let policy = RetryPolicy::default().max_delay(Duration::from_secs(2));
+    let delay = policy.delay(0);
+    assert!(delay >= Duration::from_millis(250) && delay <= Duration::from_millis(500));
+    let delay = policy.delay(1);
+    assert!(delay >= Duration::from_millis(500) && delay <= Duration::from_secs(1));
+    assert!(policy.delay(10) <= Duration::from_secs(2));
+    assert!(policy.delay(100) <= Duration::from_secs(2));
+}
+
+#[tokio::test]
+async fn test_rate_limiter() {
+    let limiter = RateLimiter::new(20.0, 2);
+    let start = Instant::now();
+    for _ in 0..4 {
+        limiter.clone().acquire().await;
+    }
+    // 2 at once, then 2 more at 50ms intervals.
+    assert!(start.elapsed() >= Duration::from_millis(90));
+}
+
+#[tokio::test]
+async fn test_retry() {
+    use crate::client::User;
+    use crate::mock::MockServer;
+    use crate::session::SessionBuilder;
+    use crate::ZfError;
+
+    let server = MockServer::start("2018000000", "passwd").await;
+    let session = |retry: RetryPolicy| {
+        SessionBuilder::new()
+            .user("2018000000")
+            .passwd("passwd")
+            .base_url(server.base_url())
+            .retry(retry.base_delay(Duration::from_millis(10)))
+            .build()
+    };
+
+    let client = session(RetryPolicy::new(2)).login().await.unwrap();
+    server.fail_next(2);
+    assert!(client.get_profile().await.is_ok());
+
+    let client = session(RetryPolicy::none()).login().await.unwrap();
+    server.fail_next(1);
+    match client.get_profile().await {
+        Err(ZfError::HttpStatus { status, .. }) => assert_eq!(status.as_u16(), 503),
+        r => panic!("Unexpected result: {:?}", r.map(|_| ())),
+    }
+}
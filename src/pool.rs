@@ -0,0 +1,207 @@
+//! Clients of many accounts for batch jobs, like querying the timetables of a class.
+//!
+//! Accounts login lazily on the first job, and expired sessions login again on the next request.
+//! Jobs run concurrently, but at most `max_concurrency` accounts access the same server at a time.
+//!
+//! ```no_run
+//! # async fn run() {
+//! use zf_tools_rs::client::User;
+//! use zf_tools_rs::parsers::{SchoolYear, Semester};
+//! use zf_tools_rs::pool::AccountPool;
+//! use zf_tools_rs::session::SessionBuilder;
+//!
+//! let pool = AccountPool::new()
+//!     .account(SessionBuilder::new().user("user1").passwd("passwd1").build())
+//!     .account(SessionBuilder::new().user("user2").passwd("passwd2").build());
+//! let results = pool
+//!     .for_each_account(|client| client.get_timetable(SchoolYear::AllYear, Semester::All))
+//!     .await;
+//! for result in results {
+//!     println!("{}: {:?}", result.user, result.result.map(|courses| courses.len()));
+//! }
+//! # }
+//! ```
+
+use crate::client::ZfClient;
+use crate::session::{SavedSession, Session};
+use crate::{Result, ZfError};
+use futures_util::future::join_all;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{OnceCell, Semaphore};
+
+/// Future returned by jobs, the same as the methods of `User` and `Environment`.
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+struct Account {
+    session: Session,
+    /// Set after login, so that each account logs in once.
+    client: OnceCell<ZfClient>,
+}
+
+/// Result of a job on an account
+#[derive(Debug)]
+pub struct AccountResult<T> {
+    /// Student number
+    pub user: String,
+    pub result: Result<T>,
+}
+
+/// Sessions of many accounts, see the module document.
+pub struct AccountPool {
+    accounts: Vec<Account>,
+    max_concurrency: usize,
+    /// Semaphore of each server, by origin of the base URL
+    limits: Mutex<HashMap<String, Arc<Semaphore>>>,
+}
+
+impl Default for AccountPool {
+    fn default() -> Self {
+        AccountPool::new()
+    }
+}
+
+impl AccountPool {
+    pub fn new() -> Self {
+        AccountPool {
+            accounts: vec![],
+            max_concurrency: 4,
+            limits: Mutex::default(),
+        }
+    }
+
+    /// Max accounts accessing one server at a time, defaults to 4.
+    pub fn max_concurrency(mut self, max_concurrency: usize) -> Self {
+        self.max_concurrency = std::cmp::max(max_concurrency, 1);
+        self
+    }
+
+    /// Add an account. Restored sessions, see `SessionBuilder::from_saved`, are used without
+    /// login until they expire.
+    pub fn account(mut self, session: Session) -> Self {
+        self.accounts.push(Account {
+            session,
+            client: OnceCell::new(),
+        });
+        self
+    }
+
+    /// Student numbers of the accounts, in the order they are added.
+    pub fn users(&self) -> Vec<&str> {
+        self.accounts
+            .iter()
+            .map(|account| account.session.user.as_str())
+            .collect()
+    }
+
+    /// Export the sessions, so that the next run does not login again.
+    pub fn save(&self) -> Vec<SavedSession> {
+        self.accounts
+            .iter()
+            .map(|account| account.session.save())
+            .collect()
+    }
+
+    fn limit(&self, session: &Session) -> Arc<Semaphore> {
+        let server = session.base_url.origin().ascii_serialization();
+        let mut limits = self
+            .limits
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        limits
+            .entry(server)
+            .or_insert_with(|| Arc::new(Semaphore::new(self.max_concurrency)))
+            .clone()
+    }
+
+    async fn login(account: &Account) -> Result<&ZfClient> {
+        account
+            .client
+            .get_or_try_init(|| async {
+                if account.session.is_logged_in() {
+                    Ok(account.session.restore())
+                } else {
+                    account.session.login().await
+                }
+            })
+            .await
+    }
+
+    /// Client of the account, which logs in if it has not.
+    pub async fn client(&self, user: &str) -> Result<ZfClient> {
+        let account = self
+            .accounts
+            .iter()
+            .find(|account| account.session.user == user)
+            .ok_or_else(|| ZfError::UnknownAccount(user.to_string()))?;
+        let limit = self.limit(&account.session);
+        let _permit = limit.acquire().await.ok();
+        Self::login(account).await.cloned()
+    }
+
+    /// Run the job on every account concurrently, and return the results in the order the
+    /// accounts are added. Errors of an account, including login errors, do not affect others.
+    pub async fn for_each_account<T, F>(&self, job: F) -> Vec<AccountResult<T>>
+    where
+        F: for<'a> Fn(&'a ZfClient) -> BoxFuture<'a, Result<T>>,
+    {
+        let job = &job;
+        let tasks = self.accounts.iter().map(|account| async move {
+            let limit = self.limit(&account.session);
+            // The semaphore is never closed.
+            let _permit = limit.acquire().await.ok();
+            let result = match Self::login(account).await {
+                Ok(client) => job(client).await,
+                Err(e) => Err(e),
+            };
+            AccountResult {
+                user: account.session.user.clone(),
+                result,
+            }
+        });
+        join_all(tasks).await
+    }
+}
+
+#[tokio::test]
+async fn test_account_pool() {
+    use crate::client::User;
+    use crate::mock::MockServer;
+    use crate::session::SessionBuilder;
+
+    let server = MockServer::start("2018000000", "passwd").await;
+    let session = |passwd: &str| {
+        SessionBuilder::new()
+            .user("2018000000")
+            .passwd(passwd)
+            .base_url(server.base_url())
+            .build()
+    };
+    let pool = AccountPool::new()
+        .max_concurrency(1)
+        .account(session("passwd"))
+        .account(session("wrong"));
+
+    let results = pool.for_each_account(|client| client.get_profile()).await;
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].result.as_ref().unwrap().name(), "王小明");
+    assert!(matches!(
+        results[1].result,
+        Err(ZfError::AuthenticationFailed(_))
+    ));
+
+    // Logged-in accounts do not login again, and expired ones login again.
+    let count = server.login_count();
+    pool.for_each_account(|client| client.get_profile()).await;
+    assert_eq!(server.login_count(), count);
+    server.expire_sessions();
+    let results = pool.for_each_account(|client| client.get_profile()).await;
+    assert!(results[0].result.is_ok());
+    assert_eq!(server.login_count(), count + 1);
+    assert!(matches!(
+        pool.client("2019000000").await,
+        Err(ZfError::UnknownAccount(_))
+    ));
+}
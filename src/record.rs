@@ -0,0 +1,293 @@
+//! Record responses of the server to files, and replay them offline.
+//!
+//! When the school changes a page, record the requests with `SessionBuilder::record_to`, and the
+//! recordings can be used as parser fixtures or replayed with `SessionBuilder::replay`. Passwords,
+//! cookies, the student number and personal fields of the profile are redacted before written.
+
+use crate::config::url;
+use crate::{Result, ZfError};
+use regex::Regex;
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Replacement of redacted values
+pub const REDACTED: &str = "***";
+
+/// Form fields holding credentials
+const CREDENTIAL_FIELDS: [&str; 3] = ["yhm", "mm", "csrftoken"];
+
+/// JSON fields holding personal information: student number, ID card number, birth date, phone
+/// number, email and address.
+const PERSONAL_FIELDS: [&str; 8] = [
+    "xh", "xh_id", "zjhm", "sfzjh", "csrq", "sjhm", "dzyx", "jtdz",
+];
+
+/// JSON field of the name, which is the student on `NAME_PAGES`, but the teacher or the class on
+/// timetables, class lists and exams.
+const NAME_FIELD: &str = "xm";
+
+/// Pages where `NAME_FIELD` is the name of the student
+const NAME_PAGES: [&str; 2] = [url::PROFILE, url::SCORE_LIST];
+
+fn is_name_page(path: &str) -> bool {
+    let page = path.split('?').next();
+    NAME_PAGES
+        .iter()
+        .any(|name_page| name_page.split('?').next() == page)
+}
+
+lazy_static::lazy_static! {
+    /// Personal fields on the profile page, like `<div id="col_xm"><p>name</p>`
+    static ref PROFILE_FIELD_REGEX: Regex = Regex::new(
+        r#"(id="col_(?:xh|xm|ywxm|zjhm|csrq|jg|sjhm|dzyx|jtdz)"[^>]*>\s*<p[^>]*>)[^<]*"#,
+    )
+    .expect("Invalid PROFILE_FIELD_REGEX");
+}
+
+/// A request and the response body of it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Recording {
+    pub method: String,
+    /// Path relative to the base url, with query string
+    pub path: String,
+    /// Form data, with credentials redacted
+    pub form: Vec<(String, String)>,
+    /// Response body, with personal information redacted
+    pub body: String,
+}
+
+fn redact_form(data: &[(&str, String)]) -> Vec<(String, String)> {
+    data.iter()
+        .map(|(key, value)| {
+            let value = if CREDENTIAL_FIELDS.contains(key) {
+                REDACTED.to_string()
+            } else {
+                value.clone()
+            };
+            (key.to_string(), value)
+        })
+        .collect()
+}
+
+fn redact_json(value: &mut Value, redact_name: bool) {
+    match value {
+        Value::Object(map) => map.iter_mut().for_each(|(key, value)| {
+            let personal =
+                PERSONAL_FIELDS.contains(&key.as_str()) || (redact_name && key == NAME_FIELD);
+            if personal && value.is_string() {
+                *value = Value::String(REDACTED.to_string());
+            } else {
+                redact_json(value, redact_name);
+            }
+        }),
+        Value::Array(list) => list
+            .iter_mut()
+            .for_each(|value| redact_json(value, redact_name)),
+        _ => {}
+    }
+}
+
+/// Write each request and response to a JSON file in the directory.
+#[derive(Debug, Clone)]
+pub struct Recorder {
+    dir: PathBuf,
+    /// Strings to be replaced everywhere, like the student number
+    secrets: Vec<String>,
+    /// Shared by the clones, so that their recordings are not overwritten.
+    count: Arc<AtomicUsize>,
+}
+
+impl Recorder {
+    pub fn new<P: AsRef<Path>>(dir: P) -> Self {
+        Recorder {
+            dir: dir.as_ref().to_path_buf(),
+            secrets: vec![],
+            count: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Also redact the string wherever it appears, like the name of the student.
+    pub fn redact<T: ToString>(mut self, secret: T) -> Self {
+        let secret = secret.to_string();
+        if !secret.is_empty() {
+            self.secrets.push(secret);
+        }
+        self
+    }
+
+    fn redact_body(&self, path: &str, body: &str) -> String {
+        let mut body = match serde_json::from_str::<Value>(body) {
+            Ok(mut json) => {
+                redact_json(&mut json, is_name_page(path));
+                serde_json::to_string_pretty(&json).unwrap_or_else(|_| body.to_string())
+            }
+            Err(_) => PROFILE_FIELD_REGEX
+                .replace_all(body, format!("${{1}}{}", REDACTED).as_str())
+                .into_owned(),
+        };
+        for secret in &self.secrets {
+            body = body.replace(secret.as_str(), REDACTED);
+        }
+        body
+    }
+
+    pub(crate) fn record(
+        &self,
+        method: &Method,
+        path: &str,
+        data: &[(&str, String)],
+        body: &str,
+    ) -> Result<()> {
+        let recording = Recording {
+            method: method.to_string(),
+            path: path.to_string(),
+            form: redact_form(data),
+            body: self.redact_body(path, body),
+        };
+        let index = self.count.fetch_add(1, Ordering::SeqCst);
+        // File name like "0001-cjcx_cxDgXscj.json"
+        let name = path
+            .split('?')
+            .next()
+            .and_then(|path| path.rsplit('/').next())
+            .unwrap_or_default()
+            .trim_end_matches(".html");
+
+        std::fs::create_dir_all(&self.dir)?;
+        let file = self.dir.join(format!("{:04}-{}.json", index, name));
+        std::fs::write(file, serde_json::to_string_pretty(&recording)?)?;
+        Ok(())
+    }
+}
+
+/// Recordings loaded from a directory, which serves the requests instead of the server.
+#[derive(Debug, Clone)]
+pub struct Replay {
+    recordings: Vec<Recording>,
+}
+
+impl Replay {
+    /// Load all the `.json` recordings in the directory, in file name order.
+    pub fn load<P: AsRef<Path>>(dir: P) -> Result<Self> {
+        let mut files: Vec<PathBuf> = std::fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .collect();
+        files.sort();
+
+        let recordings = files
+            .iter()
+            .map(|file| Ok(serde_json::from_str(&std::fs::read_to_string(file)?)?))
+            .collect::<Result<Vec<Recording>>>()?;
+        Ok(Replay { recordings })
+    }
+
+    pub fn recordings(&self) -> &[Recording] {
+        &self.recordings
+    }
+
+    /// Body of the recording with the same method, path and form. If there is no one, the last
+    /// recording with the same method and path is used.
+    pub(crate) fn find(
+        &self,
+        method: &Method,
+        path: &str,
+        data: &[(&str, String)],
+    ) -> Result<&str> {
+        let form = redact_form(data);
+        let mut candidates = self
+            .recordings
+            .iter()
+            .filter(|r| r.method == method.as_str() && r.path == path);
+
+        let recording = candidates
+            .clone()
+            .find(|r| {
+                let mut recorded = r.form.clone();
+                let mut expected = form.clone();
+                recorded.sort();
+                expected.sort();
+                recorded == expected
+            })
+            .or_else(|| candidates.next_back());
+
+        match recording {
+            Some(recording) => Ok(&recording.body),
+            None => Err(ZfError::NotRecorded {
+                method: method.to_string(),
+                path: path.to_string(),
+            }),
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_record_and_replay() {
+    use crate::client::User;
+    use crate::mock::MockServer;
+    use crate::parsers::{parse_score_list_page, SchoolYear, Semester};
+    use crate::session::SessionBuilder;
+
+    let dir = std::env::temp_dir().join(format!("zf-record-{:x}", rand::random::<u64>()));
+    let server = MockServer::start("2018000000", "passwd").await;
+    let session = SessionBuilder::new()
+        .user("2018000000")
+        .passwd("passwd")
+        .base_url(server.base_url())
+        .record_to(Recorder::new(&dir).redact("王小明"))
+        .build();
+    let client = session.login().await.unwrap();
+    client.get_profile().await.unwrap();
+    client
+        .get_score_list(SchoolYear::AllYear, Semester::All)
+        .await
+        .unwrap();
+    client
+        .get_timetable(SchoolYear::AllYear, Semester::All)
+        .await
+        .unwrap();
+    drop(server);
+
+    let replay = Replay::load(&dir).unwrap();
+    assert_eq!(replay.recordings().len(), 3);
+    let profile = &replay.recordings()[0];
+    assert_eq!(profile.path, url::PROFILE);
+    assert!(!profile.body.contains("2018000000"));
+    assert!(!profile.body.contains("王小明"));
+    assert!(!profile.body.contains("310000200001010000"));
+    assert!(profile.body.contains("普通本科生"));
+    // Recordings are fixtures for parsers.
+    assert_eq!(
+        parse_score_list_page(&replay.recordings()[1].body)
+            .unwrap()
+            .len(),
+        2
+    );
+    // Names in timetables are teachers, not the student.
+    let timetable = &replay.recordings()[2];
+    assert_eq!(timetable.path, url::TIME_TABLE);
+    assert!(timetable.body.contains("张三"));
+
+    let session = SessionBuilder::new()
+        .user("2018000000")
+        .replay(replay)
+        .build();
+    let client = session.restore();
+    let scores = client
+        .get_score_list(SchoolYear::AllYear, Semester::All)
+        .await
+        .unwrap();
+    assert_eq!(scores.len(), 2);
+    assert!(matches!(
+        client
+            .get_exam_list(SchoolYear::AllYear, Semester::All)
+            .await,
+        Err(ZfError::NotRecorded { .. })
+    ));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
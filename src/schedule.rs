@@ -0,0 +1,188 @@
+use crate::Result;
+use chrono::{Datelike, NaiveDate, NaiveTime};
+use serde::{Deserialize, Serialize};
+
+/// One class period (节次) and its clock time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Period {
+    /// 节次, start from 1
+    pub index: i32,
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+/// Day in a year, used to describe when the summer schedule takes effect.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct MonthDay {
+    pub month: u32,
+    pub day: u32,
+}
+
+/// Bell schedule of a campus
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CampusPeriods {
+    /// 校区, empty string matches any campus
+    #[serde(default)]
+    pub campus: String,
+    pub periods: Vec<Period>,
+    /// Periods used in summer, empty to use `periods` all the year
+    #[serde(default)]
+    pub summer_periods: Vec<Period>,
+}
+
+/// Class-period clock schedule (节次时间表), which maps period index of a course to real time.
+///
+/// It can be loaded from JSON or TOML like:
+///
+/// ```toml
+/// summer_start = { month = 5, day = 1 }
+/// summer_end = { month = 9, day = 30 }
+///
+/// [[campuses]]
+/// campus = "奉贤校区"
+/// periods = [
+///     { index = 1, start = "08:20", end = "09:05" },
+///     { index = 2, start = "09:10", end = "09:55" },
+/// ]
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeriodSchedule {
+    pub campuses: Vec<CampusPeriods>,
+    /// First day of summer schedule, inclusive
+    #[serde(default)]
+    pub summer_start: Option<MonthDay>,
+    /// Last day of summer schedule, inclusive
+    #[serde(default)]
+    pub summer_end: Option<MonthDay>,
+}
+
+impl Default for PeriodSchedule {
+    fn default() -> Self {
+        Self::sit()
+    }
+}
+
+impl PeriodSchedule {
+    /// Bell schedule of Shanghai Institute of Technology, the same for all campuses and seasons.
+    pub fn sit() -> Self {
+        const TIMES: [(u32, u32, u32, u32); 11] = [
+            (8, 20, 9, 5),
+            (9, 10, 9, 55),
+            (10, 15, 11, 0),
+            (11, 5, 11, 50),
+            (13, 0, 13, 45),
+            (13, 50, 14, 35),
+            (14, 55, 15, 40),
+            (15, 45, 16, 30),
+            (18, 0, 18, 45),
+            (18, 50, 19, 35),
+            (19, 40, 20, 25),
+        ];
+        let periods = TIMES
+            .iter()
+            .zip(1..)
+            .map(|(&(h0, m0, h1, m1), index)| Period {
+                index,
+                start: NaiveTime::from_hms_opt(h0, m0, 0).unwrap(),
+                end: NaiveTime::from_hms_opt(h1, m1, 0).unwrap(),
+            })
+            .collect();
+
+        PeriodSchedule {
+            campuses: vec![CampusPeriods {
+                campus: String::new(),
+                periods,
+                summer_periods: vec![],
+            }],
+            summer_start: None,
+            summer_end: None,
+        }
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn from_toml(toml: &str) -> Result<Self> {
+        Ok(toml::from_str(toml)?)
+    }
+
+    fn is_summer(&self, date: NaiveDate) -> bool {
+        let (start, end) = match (self.summer_start, self.summer_end) {
+            (Some(start), Some(end)) => (start, end),
+            _ => return false,
+        };
+        let today = MonthDay {
+            month: date.month(),
+            day: date.day(),
+        };
+        if start <= end {
+            start <= today && today <= end
+        } else {
+            // Range across the new year
+            start <= today || today <= end
+        }
+    }
+
+    /// Periods of the campus on the given date. Campus with empty name is the fallback.
+    pub fn periods(&self, campus: &str, date: NaiveDate) -> &[Period] {
+        let campus_periods = self
+            .campuses
+            .iter()
+            .find(|c| c.campus == campus)
+            .or_else(|| self.campuses.iter().find(|c| c.campus.is_empty()));
+
+        match campus_periods {
+            Some(c) if self.is_summer(date) && !c.summer_periods.is_empty() => &c.summer_periods,
+            Some(c) => &c.periods,
+            None => &[],
+        }
+    }
+
+    pub fn period(&self, campus: &str, date: NaiveDate, index: i32) -> Option<Period> {
+        self.periods(campus, date)
+            .iter()
+            .find(|p| p.index == index)
+            .copied()
+    }
+}
+
+#[test]
+fn test_load_period_schedule() {
+    let schedule = PeriodSchedule::from_toml(
+        r#"
+summer_start = { month = 5, day = 1 }
+summer_end = { month = 9, day = 30 }
+
+[[campuses]]
+campus = "徐汇校区"
+periods = [
+    { index = 1, start = "08:00", end = "08:45" },
+    { index = 2, start = "08:50", end = "09:35" },
+]
+summer_periods = [
+    { index = 1, start = "07:50", end = "08:35" },
+]
+
+[[campuses]]
+periods = [
+    { index = 1, start = "08:20", end = "09:05" },
+]
+"#,
+    )
+    .unwrap();
+    let winter = NaiveDate::from_ymd_opt(2021, 11, 1).unwrap();
+    let summer = NaiveDate::from_ymd_opt(2021, 6, 1).unwrap();
+
+    let period = schedule.period("徐汇校区", winter, 1).unwrap();
+    assert_eq!(period.start, NaiveTime::from_hms_opt(8, 0, 0).unwrap());
+    let period = schedule.period("徐汇校区", summer, 1).unwrap();
+    assert_eq!(period.start, NaiveTime::from_hms_opt(7, 50, 0).unwrap());
+    let period = schedule.period("奉贤校区", summer, 1).unwrap();
+    assert_eq!(period.end, NaiveTime::from_hms_opt(9, 5, 0).unwrap());
+
+    let json = serde_json::to_string(&schedule).unwrap();
+    let schedule = PeriodSchedule::from_json(&json).unwrap();
+    assert_eq!(schedule.periods("徐汇校区", winter).len(), 2);
+    assert_eq!(PeriodSchedule::sit().periods("", winter).len(), 11);
+}
@@ -1,13 +1,26 @@
+use crate::auth::{Authenticator, DirectLogin};
+use crate::captcha::{CaptchaSolver, SharedSolver};
 use crate::client::ZfClient;
 use crate::config::*;
 use crate::error::{Result, ZfError};
+use crate::policy::{RateLimiter, RetryPolicy};
+use crate::record::{Recorder, Replay};
+use crate::webvpn::WebVpn;
 use base64::{decode, encode};
+use chrono::{DateTime, Local};
 use rand::rngs::OsRng;
 use regex::Regex;
-use reqwest::header::{COOKIE, USER_AGENT};
-use reqwest::{cookie::Cookie, Client, ClientBuilder, Response, StatusCode};
+use reqwest::header::{HeaderMap, HeaderValue, COOKIE, LOCATION};
+use reqwest::{cookie::Cookie, Client, ClientBuilder, Method, Request, RequestBuilder, Response};
+use reqwest::{Proxy, StatusCode, Url};
+
+pub use reqwest::Certificate;
 use rsa::{BigUint, PaddingScheme, PublicKey, RsaPublicKey};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
 
 pub type AccountCookies = HashMap<String, HashMap<String, String>>;
 
@@ -15,22 +28,171 @@ lazy_static::lazy_static! {
     static ref CSRF_TOKEN_REGEX: Regex = Regex::new(
             "<input type=\"hidden\" id=\"csrftoken\" name=\"csrftoken\" value=\"(.*)\"/>",
         ).expect("Invalid CSRF_TOKEN_REGEX");
-    static ref DOMAIN_REGEX: Regex = Regex::new(r"http[s]?://([a-zA-Z\.0-9]+)(:[0-9]+)?/").unwrap();
 }
 
+/// Cookies of each domain. Clones share the same cookies, so that a session and all its clients
+/// see the cookies set by any of them.
+#[derive(Debug, Clone, Default)]
+pub struct CookieJar(Arc<RwLock<AccountCookies>>);
+
+impl CookieJar {
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, AccountCookies> {
+        // Cookies are always consistent, even if a panic happened while holding the lock.
+        self.0
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, AccountCookies> {
+        self.0
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Cookie header for the domain, including cookies of its parent domains.
+    pub fn get_cookie_string(&self, domain: &str) -> String {
+        let mut cookie_pairs = HashMap::<String, String>::new();
+        self.read().iter().for_each(|(key, value)| {
+            if domain.ends_with(key) {
+                for (v0, v1) in value {
+                    cookie_pairs.insert(v0.clone(), v1.clone());
+                }
+            }
+        });
+        cookie_pairs
+            .into_iter()
+            .map(|(k, v)| format!("{}={};", k, v))
+            .collect::<Vec<String>>()
+            .join("")
+    }
+
+    pub fn query(&self, domain: &str, name: &str) -> Option<String> {
+        for (key, domain_cookies) in self.read().iter() {
+            if domain.ends_with(key) {
+                if let Some(value) = domain_cookies.get(name) {
+                    return Some(value.clone());
+                }
+            }
+        }
+        None
+    }
+
+    /// Keep the cookies set by the server, `domain` is used for cookies without a domain.
+    pub fn sync<'a, T>(&self, domain: &str, cookies: T)
+    where
+        T: Iterator<Item = Cookie<'a>>,
+    {
+        let mut jar = self.write();
+        cookies.for_each(|x| {
+            let domain = x.domain().unwrap_or(domain);
+            jar.entry(String::from(domain))
+                .or_default()
+                .insert(x.name().to_string(), x.value().to_string());
+        });
+    }
+
+    pub fn clear(&self) {
+        self.write().clear();
+    }
+
+    /// Copy of all the cookies
+    pub fn cookies(&self) -> AccountCookies {
+        self.read().clone()
+    }
+}
+
+impl From<AccountCookies> for CookieJar {
+    fn from(cookies: AccountCookies) -> Self {
+        CookieJar(Arc::new(RwLock::new(cookies)))
+    }
+}
+
+#[derive(Debug, Default)]
+struct LoginState {
+    login_flag: bool,
+    login_time: Option<DateTime<Local>>,
+    /// Times of login, so that requests found the session expired know whether another request
+    /// has logged in again.
+    generation: u64,
+}
+
+/// Session of an account, which is cheap to clone. Clones share the cookies and login state, and
+/// methods take `&self`, so that one session can be used by concurrent requests.
 #[derive(Debug, Clone)]
 pub struct Session {
     pub(crate) user: String,
-    pub(crate) passwd: String,
+    /// `None` for restored sessions without password, which can not login again
+    passwd: Option<String>,
     pub(crate) client: Client,
-    pub(crate) login_flag: bool,
-    pub(crate) cookies: AccountCookies,
+    pub(crate) cookies: CookieJar,
+    /// Server address with path prefix, always ends with `/`
+    pub(crate) base_url: Url,
+    state: Arc<Mutex<LoginState>>,
+    /// Held while logging in, so that concurrent requests do not login at the same time.
+    login_lock: Arc<tokio::sync::Mutex<()>>,
+    pub(crate) recorder: Option<Recorder>,
+    pub(crate) replay: Option<Arc<Replay>>,
+    pub(crate) captcha_solver: Option<SharedSolver>,
+    pub(crate) retry: RetryPolicy,
+    pub(crate) rate_limiter: Option<RateLimiter>,
+    pub(crate) webvpn: Option<WebVpn>,
+    pub(crate) authenticator: Arc<dyn Authenticator>,
+    #[cfg(feature = "sqlite")]
+    pub(crate) cache: Option<crate::cache::Cache>,
+}
+
+/// Session state that can be saved and restored later without login again.
+///
+/// Password is never saved, set it on `SessionBuilder` if the restored session should be able to
+/// login again after expired.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedSession {
+    pub user: String,
+    pub cookies: AccountCookies,
+    pub login_time: Option<DateTime<Local>>,
+    pub base_url: String,
+}
+
+impl SavedSession {
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        std::fs::write(path, self.to_json()?)?;
+        Ok(())
+    }
+
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
 }
 
 #[derive(Default)]
 pub struct SessionBuilder {
     user: Option<String>,
     passwd: Option<String>,
+    base_url: Option<String>,
+    saved: Option<SavedSession>,
+    recorder: Option<Recorder>,
+    replay: Option<Replay>,
+    captcha_solver: Option<SharedSolver>,
+    connect_timeout: Option<Duration>,
+    timeout: Option<Duration>,
+    retry: Option<RetryPolicy>,
+    rate_limiter: Option<RateLimiter>,
+    proxy: Option<String>,
+    root_certificates: Vec<Certificate>,
+    user_agent: Option<String>,
+    headers: HeaderMap,
+    webvpn: Option<WebVpn>,
+    authenticator: Option<Arc<dyn Authenticator>>,
+    #[cfg(feature = "sqlite")]
+    cache: Option<crate::cache::Cache>,
 }
 
 impl SessionBuilder {
@@ -38,6 +200,17 @@ impl SessionBuilder {
         SessionBuilder::default()
     }
 
+    /// Restore user, cookies and server address from a saved session. Use `Session::restore` to
+    /// get the client without login.
+    pub fn from_saved(saved: SavedSession) -> Self {
+        SessionBuilder {
+            user: Some(saved.user.clone()),
+            base_url: Some(saved.base_url.clone()),
+            saved: Some(saved),
+            ..SessionBuilder::default()
+        }
+    }
+
     pub fn user<T: ToString>(mut self, user: T) -> Self {
         self.user = Some(user.to_string());
         self
@@ -48,82 +221,358 @@ impl SessionBuilder {
         self
     }
 
+    /// Server address of the 正方 deployment, like `https://jwxt.example.edu.cn:8443/jwglxt/`.
+    ///
+    /// Scheme, host, port and path prefix are all respected. Defaults to `DEFAULT_BASE_URL`.
+    pub fn base_url<T: ToString>(mut self, base_url: T) -> Self {
+        self.base_url = Some(base_url.to_string());
+        self
+    }
+
+    /// Record the pages requested by the client, see `record` module.
+    pub fn record_to(mut self, recorder: Recorder) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    /// Serve the requests of the client from recordings instead of the server. Password is not
+    /// required, and use `Session::restore` to get the client.
+    pub fn replay(mut self, replay: Replay) -> Self {
+        self.replay = Some(replay);
+        self
+    }
+
+    /// Answer the captcha on the login page if it is shown, see `captcha` module.
+    pub fn captcha_solver<T: CaptchaSolver + 'static>(mut self, solver: T) -> Self {
+        self.captcha_solver = Some(SharedSolver(Arc::new(solver)));
+        self
+    }
+
+    /// Timeout of connecting to the server, defaults to 10 seconds.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Timeout of a request, from connecting to reading the whole response. Defaults to 30
+    /// seconds.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Retry policy of idempotent requests, defaults to `RetryPolicy::default()`.
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    /// Limit the request rate of the session, including login requests. No limit by default.
+    pub fn rate_limit(mut self, rate_limiter: RateLimiter) -> Self {
+        self.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    /// Send all requests, including HTTPS ones, through the proxy, like `http://127.0.0.1:8080`.
+    /// `socks5://` proxies are not supported.
+    pub fn proxy<T: ToString>(mut self, proxy: T) -> Self {
+        self.proxy = Some(proxy.to_string());
+        self
+    }
+
+    /// Trust the root certificate besides the system ones, for servers behind a gateway with an
+    /// internal CA. Load it with `Certificate::from_pem` or `Certificate::from_der`.
+    pub fn root_certificate(mut self, certificate: Certificate) -> Self {
+        self.root_certificates.push(certificate);
+        self
+    }
+
+    /// User-Agent of the requests, defaults to `DEFAULT_USER_AGENT`.
+    pub fn user_agent<T: ToString>(mut self, user_agent: T) -> Self {
+        self.user_agent = Some(user_agent.to_string());
+        self
+    }
+
+    /// Headers sent with every request. Cookies are managed by the session, and a `Cookie`
+    /// header here is replaced.
+    pub fn default_headers(mut self, headers: HeaderMap) -> Self {
+        self.headers.extend(headers);
+        self
+    }
+
+    /// Access the server through the WebVPN gateway, see `webvpn` module.
+    pub fn webvpn(mut self, webvpn: WebVpn) -> Self {
+        self.webvpn = Some(webvpn);
+        self
+    }
+
+    /// How to login, defaults to `DirectLogin`. See `auth` module.
+    pub fn authenticator<T: Authenticator + 'static>(mut self, authenticator: T) -> Self {
+        self.authenticator = Some(Arc::new(authenticator));
+        self
+    }
+
+    /// Read and store fetched data in the local cache, see `cache` module.
+    #[cfg(feature = "sqlite")]
+    pub fn cache(mut self, cache: crate::cache::Cache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
     pub fn build(self) -> Session {
+        let mut base_url = self
+            .base_url
+            .unwrap_or_else(|| DEFAULT_BASE_URL.to_string());
+        // Without the trailing slash, the last path segment would be replaced while joining.
+        if !base_url.ends_with('/') {
+            base_url.push('/');
+        }
+        let base_url = Url::parse(&base_url)
+            .unwrap_or_else(|e| panic!("Invalid base url {} in SessionBuilder: {}", base_url, e));
+
+        // A restored session works without password until it expires.
+        let restored = self.saved.is_some() || self.replay.is_some();
+
+        let user = self.user.unwrap_or_else(|| {
+            panic!("User is required in SessionBuilder, please call user method.")
+        });
+        let mut client = ClientBuilder::new()
+            .redirect(reqwest::redirect::Policy::none())
+            .connect_timeout(self.connect_timeout.unwrap_or(Duration::from_secs(10)))
+            .timeout(self.timeout.unwrap_or(Duration::from_secs(30)))
+            .user_agent(
+                self.user_agent
+                    .unwrap_or_else(|| DEFAULT_USER_AGENT.to_string()),
+            )
+            .default_headers(self.headers);
+        if let Some(proxy) = self.proxy {
+            let proxy = Proxy::all(&proxy)
+                .unwrap_or_else(|e| panic!("Invalid proxy {} in SessionBuilder: {}", proxy, e));
+            client = client.proxy(proxy);
+        }
+        for certificate in self.root_certificates {
+            client = client.add_root_certificate(certificate);
+        }
+        let client = client
+            .build()
+            .unwrap_or_else(|e| panic!("Failed to build the HTTP client: {}", e));
+
+        // The student number is always redacted from recordings.
+        let recorder = self.recorder.map(|recorder| recorder.redact(&user));
+
         Session {
-            user: self.user.unwrap_or_else(|| {
-                panic!("User is required in SessionBuilder, please call user method.")
-            }),
-            passwd: self.passwd.unwrap_or_else(|| {
-                panic!("Passwd is required in SessionBuilder, please call passwd method.")
-            }),
-            client: ClientBuilder::new()
-                .redirect(reqwest::redirect::Policy::none())
-                .build()
-                .unwrap(),
-            login_flag: false,
-            cookies: HashMap::default(),
+            user,
+            passwd: match self.passwd {
+                None if !restored => {
+                    panic!("Passwd is required in SessionBuilder, please call passwd method.")
+                }
+                passwd => passwd,
+            },
+            client,
+            cookies: self
+                .saved
+                .as_ref()
+                .map(|saved| CookieJar::from(saved.cookies.clone()))
+                .unwrap_or_default(),
+            base_url,
+            state: Arc::new(Mutex::new(LoginState {
+                login_flag: restored,
+                login_time: self.saved.and_then(|saved| saved.login_time),
+                generation: 0,
+            })),
+            login_lock: Arc::default(),
+            recorder,
+            replay: self.replay.map(Arc::new),
+            captcha_solver: self.captcha_solver,
+            retry: self.retry.unwrap_or_default(),
+            rate_limiter: self.rate_limiter,
+            webvpn: self.webvpn,
+            authenticator: self.authenticator.unwrap_or_else(|| Arc::new(DirectLogin)),
+            #[cfg(feature = "sqlite")]
+            cache: self.cache,
         }
     }
 }
 
-pub fn encrypt_in_rsa(
-    message: &[u8],
-    public_key: Vec<u8>,
-    exponent: Vec<u8>,
-) -> anyhow::Result<String> {
+pub fn encrypt_in_rsa(message: &[u8], public_key: Vec<u8>, exponent: Vec<u8>) -> Result<String> {
     let key = BigUint::from_bytes_be(&public_key);
     let exp = BigUint::from_bytes_be(&exponent);
     let mut rng = OsRng;
     let padding = PaddingScheme::new_pkcs1v15_encrypt();
-    let publickey = RsaPublicKey::new(key, exp)?;
+    let publickey = RsaPublicKey::new(key, exp).map_err(|e| ZfError::Encryption(e.to_string()))?;
     let enc_data = publickey
         .encrypt(&mut rng, padding, message)
-        .expect("failed to encrypt");
+        .map_err(|e| ZfError::Encryption(e.to_string()))?;
     Ok(encode(enc_data))
 }
 
+/// Host of the url, like `jwxt.sit.edu.cn` for `http://jwxt.sit.edu.cn:80/jwglxt/`.
+#[deprecated(note = "the server address is configurable now, use `Session::domain` instead")]
 pub fn domain(url: &str) -> Option<String> {
-    DOMAIN_REGEX.captures(url).map(|x| x[1].to_string())
+    Url::parse(url).ok()?.host_str().map(String::from)
 }
 
 impl Session {
-    async fn post_with_auto_redirect(
-        &mut self,
-        start_page: &str,
-        params: [(&str, &str); 4],
-    ) -> anyhow::Result<Response> {
+    fn state(&self) -> std::sync::MutexGuard<'_, LoginState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Export user, cookies, login time and server address of the session.
+    pub fn save(&self) -> SavedSession {
+        SavedSession {
+            user: self.user.clone(),
+            cookies: self.cookies.cookies(),
+            login_time: self.state().login_time,
+            base_url: self.base_url.to_string(),
+        }
+    }
+
+    /// Whether the session has logged in or been restored.
+    pub fn is_logged_in(&self) -> bool {
+        self.state().login_flag
+    }
+
+    pub fn login_time(&self) -> Option<DateTime<Local>> {
+        self.state().login_time
+    }
+
+    /// Times of login since the session is built, see `relogin`.
+    pub(crate) fn generation(&self) -> u64 {
+        self.state().generation
+    }
+
+    /// Password to login, a restored session without password is expired once it needs to login.
+    pub(crate) fn passwd(&self) -> Result<&str> {
+        self.passwd.as_deref().ok_or(ZfError::SessionExpired(None))
+    }
+
+    /// Get a client on the current cookies without login, usually after `SessionBuilder::from_saved`.
+    pub fn restore(&self) -> ZfClient {
+        ZfClient {
+            user: self.user.clone(),
+            session: self.clone(),
+        }
+    }
+
+    /// Resolve a page path (see `config::url`) against the base URL of the session, and through
+    /// the WebVPN gateway if it is set.
+    pub fn url(&self, path: &str) -> Result<Url> {
+        self.route(self.base_url.join(path)?)
+    }
+
+    /// URL to request for the URL of a site, which goes through the WebVPN gateway if it is set.
+    pub fn route(&self, url: Url) -> Result<Url> {
+        match &self.webvpn {
+            Some(webvpn) => webvpn.rewrite(&url),
+            None => Ok(url),
+        }
+    }
+
+    /// Host of the server, which is used as the default cookie domain.
+    pub fn domain(&self) -> &str {
+        self.base_url.host_str().unwrap_or_default()
+    }
+
+    /// Send a request with the cookies of its target domain, and keep the cookies the server
+    /// sets. GET requests are retried by the retry policy.
+    pub(crate) async fn execute(&self, request: RequestBuilder) -> Result<Response> {
+        let request = request.build()?;
+        let idempotent = matches!(*request.method(), Method::GET | Method::HEAD);
+        self.execute_with_retry(request, idempotent).await
+    }
+
+    /// Like `execute`, but the request is a query which is safe to retry whatever the method is.
+    pub(crate) async fn execute_query(&self, request: RequestBuilder) -> Result<Response> {
+        self.execute_with_retry(request.build()?, true).await
+    }
+
+    async fn execute_with_retry(&self, request: Request, idempotent: bool) -> Result<Response> {
+        let mut retries = 0;
+        loop {
+            let attempt = match request.try_clone() {
+                Some(attempt) if idempotent && retries < self.retry.max_retries() => attempt,
+                _ => return self.execute_once(request).await,
+            };
+            let result = self.execute_once(attempt).await;
+            let retryable = match &result {
+                Ok(response) => response.status().is_server_error(),
+                Err(ZfError::Transport(e)) => e.is_timeout() || e.is_connect() || e.is_request(),
+                Err(_) => false,
+            };
+            if !retryable {
+                return result;
+            }
+            tokio::time::sleep(self.retry.delay(retries)).await;
+            retries += 1;
+        }
+    }
+
+    async fn execute_once(&self, mut request: Request) -> Result<Response> {
+        if let Some(rate_limiter) = &self.rate_limiter {
+            rate_limiter.acquire().await;
+        }
+        let domain = request.url().host_str().unwrap_or_default().to_string();
+        if let Ok(cookie) = HeaderValue::from_str(&self.cookies.get_cookie_string(&domain)) {
+            request.headers_mut().insert(COOKIE, cookie);
+        }
+
+        let response = self.client.execute(request).await?;
+        self.cookies.sync(&domain, response.cookies());
+        Ok(response)
+    }
+
+    /// Whether the response is the login page, or a redirection to it, which means the session
+    /// has not logged in or has expired. The login page of the WebVPN portal counts too.
+    pub(crate) fn is_login_page(&self, response: &Response) -> bool {
+        let login_url = match self.url(url::LOGIN) {
+            Ok(url) => url,
+            Err(_) => return false,
+        };
+        let target = if response.status().is_redirection() {
+            response
+                .headers()
+                .get(LOCATION)
+                .and_then(|location| location.to_str().ok())
+                .and_then(|location| response.url().join(location).ok())
+        } else {
+            Some(response.url().clone())
+        };
+        target.is_some_and(|url| {
+            url.path() == login_url.path()
+                || self
+                    .webvpn
+                    .as_ref()
+                    .is_some_and(|webvpn| webvpn.is_portal_login(&url))
+        })
+    }
+
+    /// Post the form, and follow the redirections with GET requests.
+    pub(crate) async fn post_with_auto_redirect(
+        &self,
+        start_page: Url,
+        params: &[(&str, &str)],
+    ) -> Result<Response> {
         let mut remain_redirect = 10;
-        let mut next_hop = start_page.to_string();
-
-        let mut response = self
-            .client
-            .post(&next_hop)
-            .header(USER_AGENT, USERAGENT)
-            .header(COOKIE, self.get_cookie_string("jwxt.sit.edu.cn"))
-            .form(&params)
-            .send()
-            .await?;
-        self.sync_cookies("jwxt.sit.edu.cn", response.cookies());
+        let mut next_hop = start_page;
+
+        let request = self.client.post(next_hop.clone()).form(params);
+        let mut response = self.execute(request).await?;
 
         while remain_redirect > 0 && response.status() == StatusCode::FOUND {
-            let redirect_url = response.headers().get("location");
-            if redirect_url.is_none() {
-                return Ok(response);
-            }
-            let t = redirect_url.unwrap().to_str().unwrap().to_string();
-            next_hop = if domain(&t).is_none() {
-                format!("http://{}/{}", domain(&next_hop).unwrap(), t)
-            } else {
-                t
+            let redirect_url = match response.headers().get(LOCATION) {
+                Some(location) => location
+                    .to_str()
+                    .map_err(|e| ZfError::parse("Location", e).at(next_hop.path()))?
+                    .to_string(),
+                None => return Ok(response),
             };
-            response = self
-                .client
-                .get(&next_hop)
-                .header(USER_AGENT, USERAGENT)
-                .header(COOKIE, self.get_cookie_string("jwxt.sit.edu.cn"))
-                .send()
-                .await?;
-            self.sync_cookies("jwxt.sit.edu.cn", response.cookies());
+            // Location may be absolute, or relative to the current page.
+            next_hop = self.route(next_hop.join(&redirect_url)?)?;
+
+            let request = self.client.get(next_hop.clone());
+            response = self.execute(request).await?;
             remain_redirect -= 1;
         }
         Ok(response)
@@ -131,72 +580,41 @@ impl Session {
 
     // Cookies function
     pub fn get_cookie_string(&self, domain: &str) -> String {
-        let mut cookie_pairs = HashMap::<String, String>::new();
-        self.cookies.iter().for_each(|(key, value)| {
-            if domain.ends_with(key) {
-                for (v0, v1) in value {
-                    cookie_pairs.insert(v0.clone(), v1.clone());
-                }
-            }
-        });
-        cookie_pairs
-            .into_iter()
-            .map(|(k, v)| format!("{}={};", k, v))
-            .collect::<Vec<String>>()
-            .join("")
+        self.cookies.get_cookie_string(domain)
     }
 
-    pub fn query_cookie(&self, domain: &str, name: &str) -> Option<&String> {
-        for (key, domain_cookies) in self.cookies.iter() {
-            if domain.ends_with(key) {
-                if let Some(value) = domain_cookies.get(name) {
-                    return Some(value);
-                }
-            }
-        }
-        None
+    pub fn query_cookie(&self, domain: &str, name: &str) -> Option<String> {
+        self.cookies.query(domain, name)
     }
 
-    pub fn sync_cookies<'a, T>(&mut self, domain: &str, cookies: T)
+    pub fn sync_cookies<'a, T>(&self, domain: &str, cookies: T)
     where
         T: Iterator<Item = Cookie<'a>>,
     {
-        cookies.for_each(|x| {
-            let domain = x.domain().unwrap_or(domain);
-            let mut domain_cookies = if let Some(c) = self.cookies.remove(domain) {
-                c
-            } else {
-                HashMap::new()
-            };
-            domain_cookies.insert(x.name().to_string(), x.value().to_string());
-            self.cookies.insert(String::from(domain), domain_cookies);
-        });
+        self.cookies.sync(domain, cookies)
     }
 
     // Passwd ras function
-    pub async fn get_ras_public_key(&mut self) -> anyhow::Result<(Vec<u8>, Vec<u8>)> {
+    pub async fn get_ras_public_key(&self) -> Result<(Vec<u8>, Vec<u8>)> {
         #[derive(Debug, serde::Deserialize)]
         struct RsaPublicKey {
             modulus: String,
             exponent: String,
         }
 
-        let resp = self
-            .client
-            .get(url::RSA_PUBLIC_KEY)
-            .header(USER_AGENT, USERAGENT)
-            .header(COOKIE, self.get_cookie_string("jwxt.sit.edu.cn"))
-            .send()
-            .await?;
-
-        self.sync_cookies("jwxt.sit.edu.cn", resp.cookies());
-        let public_key = resp.json::<RsaPublicKey>().await?;
-        let modulus = decode(public_key.modulus)?;
-        let exponent = decode(public_key.exponent)?;
+        let request = self.client.get(self.url(url::RSA_PUBLIC_KEY)?);
+        let resp = self.execute(request).await?;
+        let text = resp.text().await?;
+        let public_key = serde_json::from_str::<RsaPublicKey>(&text)
+            .map_err(|e| ZfError::from(e).at(url::RSA_PUBLIC_KEY))?;
+        let modulus = decode(public_key.modulus)
+            .map_err(|e| ZfError::parse("modulus", e).at(url::RSA_PUBLIC_KEY))?;
+        let exponent = decode(public_key.exponent)
+            .map_err(|e| ZfError::parse("exponent", e).at(url::RSA_PUBLIC_KEY))?;
         Ok((modulus, exponent))
     }
 
-    pub fn get_csrf_token(&self, login_page: &str) -> anyhow::Result<String> {
+    pub fn get_csrf_token(&self, login_page: &str) -> Result<String> {
         let text = login_page;
         if let Some(token_tag) = CSRF_TOKEN_REGEX.captures(text) {
             let token = &token_tag[1];
@@ -205,60 +623,175 @@ impl Session {
         Ok(String::new())
     }
 
-    fn parse_err_message(content: &str) -> String {
-        use scraper::{Html, Selector};
-        let document = Html::parse_document(content);
-        let err_node: String = document
-            .select(
-                &Selector::parse("div#home.tab-pane.in.active p#tips.bg_danger.sl_danger").unwrap(),
-            )
-            .next()
-            .unwrap()
-            .text()
-            .collect();
-        err_node.trim().to_string()
+    /// Fetch the captcha image and ask the solver, `CaptchaRequired` if there is no solver.
+    pub(crate) async fn solve_captcha(&self, mut captcha_url: Url) -> Result<String> {
+        let solver = self
+            .captcha_solver
+            .as_ref()
+            .ok_or(ZfError::CaptchaRequired)?;
+        // The timestamp avoids a cached image, as the browser does.
+        captcha_url
+            .query_pairs_mut()
+            .append_pair("time", &Local::now().timestamp_millis().to_string());
+        let response = self.execute(self.client.get(captcha_url)).await?;
+        if !response.status().is_success() {
+            return Err(ZfError::HttpStatus {
+                status: response.status(),
+                url: response.url().to_string(),
+            });
+        }
+        let image = response.bytes().await?;
+        solver.0.solve(&image).await
     }
 
     // Login function
-    pub async fn login(&mut self) -> Result<ZfClient> {
-        // Get login page for the first cookie
-        self.cookies.clear();
+    pub async fn login(&self) -> Result<ZfClient> {
+        let _guard = self.login_lock.lock().await;
+        self.authenticate().await?;
+        Ok(ZfClient {
+            user: self.user.clone(),
+            session: self.clone(),
+        })
+    }
 
-        let login_page = self
-            .client
-            .get(url::HOME)
-            .header(USER_AGENT, USERAGENT)
-            .send()
-            .await?;
-        self.sync_cookies("jwxt.sit.edu.cn", login_page.cookies());
-
-        let text = login_page.text().await?;
-        let token = self.get_csrf_token(&text)?;
-
-        if let Ok((public_key, exponent)) = self.get_ras_public_key().await {
-            let encrypted_passwd = encrypt_in_rsa(self.passwd.as_bytes(), public_key, exponent)?;
-
-            let params = [
-                ("csrftoken", token.as_str()),
-                ("language", "zh_CN"),
-                ("yhm", &self.user.clone()),
-                ("mm", &encrypted_passwd),
-            ];
-
-            let final_response = self.post_with_auto_redirect(url::LOGIN, params).await?;
-            return if final_response.url().to_string().starts_with(url::LOGIN) {
-                let text = final_response.text().await?;
-                let error = Self::parse_err_message(&text);
-
-                Err(ZfError::SessionError(error).into())
-            } else {
-                self.login_flag = true;
-                Ok(ZfClient {
-                    user: self.user.clone(),
-                    session: self.clone(),
-                })
-            };
+    /// Login again after a request found the session expired, `generation` is the one when the
+    /// request was sent. If another request has logged in since then, it does not login again.
+    pub(crate) async fn relogin(&self, generation: u64) -> Result<()> {
+        let _guard = self.login_lock.lock().await;
+        if self.generation() != generation {
+            return Ok(());
         }
-        Err(ZfError::PublicKeyError.into())
+        self.authenticate().await
     }
+
+    /// Login with the stored credentials by the authenticator, and keep the new cookies in the
+    /// session. Callers hold the login lock.
+    async fn authenticate(&self) -> Result<()> {
+        // Without password, a failed attempt would count towards captcha or account lock.
+        self.passwd()?;
+        // Start from a new session, as cookies of the expired one may confuse the server.
+        self.cookies.clear();
+        if let Some(webvpn) = &self.webvpn {
+            webvpn.login(self).await?;
+        }
+        self.authenticator.login(self).await?;
+
+        let mut state = self.state();
+        state.login_flag = true;
+        state.login_time = Some(Local::now());
+        state.generation += 1;
+        Ok(())
+    }
+}
+
+#[test]
+fn test_session_base_url() {
+    let session = SessionBuilder::new().user("user").passwd("passwd").build();
+    assert_eq!(
+        session.url(url::LOGIN).unwrap().as_str(),
+        "http://jwxt.sit.edu.cn/jwglxt/xtgl/login_slogin.html"
+    );
+    assert_eq!(session.domain(), "jwxt.sit.edu.cn");
+
+    let session = SessionBuilder::new()
+        .user("user")
+        .passwd("passwd")
+        .base_url("https://127.0.0.1:8443/zf/jwglxt")
+        .build();
+    assert_eq!(
+        session.url(url::SCORE_LIST).unwrap().as_str(),
+        "https://127.0.0.1:8443/zf/jwglxt/cjcx/cjcx_cxDgXscj.html?doType=query&gnmkdm=N305005"
+    );
+    assert_eq!(session.domain(), "127.0.0.1");
+}
+
+#[test]
+fn test_restore_saved_session() {
+    let mut cookies = AccountCookies::new();
+    cookies.insert(
+        "127.0.0.1".to_string(),
+        HashMap::from([("JSESSIONID".to_string(), "ABCDEF".to_string())]),
+    );
+    let saved = SavedSession {
+        user: "user".to_string(),
+        cookies,
+        login_time: Some(Local::now()),
+        base_url: "http://127.0.0.1:8080/jwglxt/".to_string(),
+    };
+    let json = saved.to_json().unwrap();
+
+    let session = SessionBuilder::from_saved(SavedSession::from_json(&json).unwrap()).build();
+    assert!(session.is_logged_in());
+    assert_eq!(session.get_cookie_string("127.0.0.1"), "JSESSIONID=ABCDEF;");
+    assert_eq!(session.save().base_url, saved.base_url);
+    assert_eq!(session.save().login_time, saved.login_time);
+}
+
+#[tokio::test]
+async fn test_restored_session_expired() {
+    use crate::client::User;
+    use crate::mock::MockServer;
+
+    let server = MockServer::start("2018000000", "passwd").await;
+    let client = SessionBuilder::new()
+        .user("2018000000")
+        .passwd("passwd")
+        .base_url(server.base_url())
+        .build()
+        .login()
+        .await
+        .unwrap();
+    let client = SessionBuilder::from_saved(client.save()).build().restore();
+    assert!(client.get_profile().await.is_ok());
+
+    // It does not try to login without password.
+    server.expire_sessions();
+    assert!(matches!(
+        client.get_profile().await,
+        Err(ZfError::SessionExpired(None))
+    ));
+    assert_eq!(server.login_attempts(), 1);
+}
+
+#[tokio::test]
+async fn test_proxy_and_headers() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    // A proxy which records the request and returns an empty page.
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let proxy = format!("http://{}", listener.local_addr().unwrap());
+    let server = tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut request = vec![0u8; 4096];
+        let size = stream.read(&mut request).await.unwrap();
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        String::from_utf8_lossy(&request[..size]).to_lowercase()
+    });
+
+    let mut headers = HeaderMap::new();
+    headers.insert("x-campus-tool", HeaderValue::from_static("timetable"));
+    let session = SessionBuilder::new()
+        .user("user")
+        .passwd("passwd")
+        .base_url("http://jwxt.example.edu.cn/jwglxt/")
+        .proxy(&proxy)
+        .user_agent("zf-tools-rs")
+        .default_headers(headers)
+        .build();
+    let request = session.client.get(session.url(url::INDEX).unwrap());
+    assert!(session
+        .execute(request)
+        .await
+        .unwrap()
+        .status()
+        .is_success());
+
+    let request = server.await.unwrap();
+    assert!(request.starts_with("get http://jwxt.example.edu.cn/jwglxt/xtgl/index_initmenu.html"));
+    assert!(request.contains("user-agent: zf-tools-rs\r\n"));
+    assert!(request.contains("x-campus-tool: timetable\r\n"));
 }
@@ -0,0 +1,254 @@
+//! Watch the score list of a term, and notify when a score is published or changed.
+
+mod sink;
+
+pub use sink::{ChannelSink, Sink, SmtpSink, StdoutSink, WebhookSink};
+
+use crate::client::{User, ZfClient};
+use crate::parsers::{SchoolYear, Score, Semester};
+use crate::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Change of the score list found by the watcher
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ScoreEvent {
+    /// A score is published
+    NewScore(Score),
+    /// The grade or grade point of a published score is changed
+    ScoreChanged { old: Score, new: Score },
+}
+
+impl fmt::Display for ScoreEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScoreEvent::NewScore(score) => {
+                write!(f, "New score: {} {}", score.course(), score.grade())
+            }
+            ScoreEvent::ScoreChanged { old, new } => write!(
+                f,
+                "Score changed: {} {} -> {}",
+                new.course(),
+                old.grade(),
+                new.grade()
+            ),
+        }
+    }
+}
+
+/// Identify a score in the list, attempts of a retaken course are different scores.
+fn score_key(score: &Score) -> String {
+    format!(
+        "{}|{}|{}|{:?}|{:?}",
+        score.course_id, score.class_id, score.school_year, score.semester, score.exam_nature
+    )
+}
+
+/// Events from the last snapshot to the current score list.
+pub fn diff_scores(last: &[Score], current: &[Score]) -> Vec<ScoreEvent> {
+    let last: HashMap<String, &Score> = last.iter().map(|s| (score_key(s), s)).collect();
+    current
+        .iter()
+        .filter_map(|score| match last.get(&score_key(score)) {
+            None => Some(ScoreEvent::NewScore(score.clone())),
+            Some(old) if old.grade != score.grade || old.grade_point != score.grade_point => {
+                Some(ScoreEvent::ScoreChanged {
+                    old: (*old).clone(),
+                    new: score.clone(),
+                })
+            }
+            Some(_) => None,
+        })
+        .collect()
+}
+
+/// Poll the score list of a term, and send the changes to the sinks.
+///
+/// The first check only takes the snapshot, unless there is a snapshot file saved before. On
+/// errors, the interval is doubled until `max_backoff` so that it does not overload the server.
+pub struct ScoreWatcher {
+    school_year: SchoolYear,
+    semester: Semester,
+    interval: Duration,
+    max_backoff: Duration,
+    snapshot_path: Option<PathBuf>,
+    snapshot: Option<Vec<Score>>,
+    sinks: Vec<Box<dyn Sink>>,
+}
+
+impl ScoreWatcher {
+    pub fn new(school_year: SchoolYear, semester: Semester) -> Self {
+        ScoreWatcher {
+            school_year,
+            semester,
+            interval: Duration::from_secs(10 * 60),
+            max_backoff: Duration::from_secs(2 * 60 * 60),
+            snapshot_path: None,
+            snapshot: None,
+            sinks: vec![],
+        }
+    }
+
+    /// Time between two checks, defaults to 10 minutes.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Upper limit of the interval after continuous errors, defaults to 2 hours.
+    pub fn max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// Persist the snapshot to the JSON file, so that changes during restarts are not missed.
+    pub fn snapshot_path<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.snapshot_path = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn sink<T: Sink + 'static>(mut self, sink: T) -> Self {
+        self.sinks.push(Box::new(sink));
+        self
+    }
+
+    fn load_snapshot(&self) -> Result<Option<Vec<Score>>> {
+        match &self.snapshot_path {
+            Some(path) if path.exists() => {
+                let text = std::fs::read_to_string(path)?;
+                Ok(Some(serde_json::from_str(&text)?))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn save_snapshot(&self, scores: &[Score]) -> Result<()> {
+        if let Some(path) = &self.snapshot_path {
+            std::fs::write(path, serde_json::to_string_pretty(scores)?)?;
+        }
+        Ok(())
+    }
+
+    /// Fetch the score list once, and send the changes since the last check to the sinks.
+    pub async fn check(&mut self, client: &ZfClient) -> Result<Vec<ScoreEvent>> {
+        let scores = client
+            .get_score_list(self.school_year.clone(), self.semester.clone())
+            .await?;
+        if self.snapshot.is_none() {
+            self.snapshot = self.load_snapshot()?;
+        }
+        let events = match &self.snapshot {
+            Some(last) => diff_scores(last, &scores),
+            None => vec![],
+        };
+        // Notify before saving, so that events are sent again rather than lost on sink errors.
+        if !events.is_empty() {
+            for sink in &self.sinks {
+                sink.notify(&events).await?;
+            }
+        }
+        self.save_snapshot(&scores)?;
+        self.snapshot = Some(scores);
+        Ok(events)
+    }
+
+    /// Check forever. Returns only on errors which retrying does not help, namely a wrong password,
+    /// a captcha which can not be solved, or an expired session without password. Retrying the
+    /// login with a wrong password may lock the account, while network errors are retried.
+    pub async fn run(&mut self, client: &ZfClient) -> Result<()> {
+        let mut delay = self.interval;
+        loop {
+            match self.check(client).await {
+                Ok(_) => delay = self.interval,
+                Err(e) if e.is_login_rejected() => return Err(e),
+                Err(_) => delay = std::cmp::min(delay * 2, self.max_backoff),
+            }
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_score_watcher() {
+    use crate::config::url;
+    use crate::mock::MockServer;
+    use crate::session::SessionBuilder;
+
+    let server = MockServer::start("2018000000", "passwd").await;
+    let client = SessionBuilder::new()
+        .user("2018000000")
+        .passwd("passwd")
+        .base_url(server.base_url())
+        .build()
+        .login()
+        .await
+        .unwrap();
+
+    let path = std::env::temp_dir().join(format!("zf-scores-{:x}.json", rand::random::<u64>()));
+    let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+    let mut watcher = ScoreWatcher::new(SchoolYear::SomeYear(2020), Semester::FirstTerm)
+        .snapshot_path(&path)
+        .sink(ChannelSink::new(sender.clone()));
+    assert!(watcher.check(&client).await.unwrap().is_empty());
+
+    server.set_page(
+        url::SCORE_LIST,
+        r#"{"items": [
+            {"cj": "90", "jxb_id": "B3F0EBAA2E6C4FD7E0530100007F3B31", "kch": "B1020011", "kcmc": "大学物理A1", "xf": "4.0", "xnmmc": "2020-2021", "xqm": "3"},
+            {"cj": "良好", "jxb_id": "B3F0EBAA2E6C4FD7E0530100007F3B32", "jd": "3.50", "kch": "B5020021", "kcmc": "音乐鉴赏", "ksxz": "正常考试", "xf": "2.0", "xnmmc": "2020-2021", "xqm": "3"},
+            {"cj": "合格", "jxb_id": "3", "kch": "B7000001", "kcmc": "军训", "xf": "1.0", "xnmmc": "2020-2021", "xqm": "3"}
+        ]}"#,
+    );
+    // A new watcher picks up the persisted snapshot.
+    let mut watcher = ScoreWatcher::new(SchoolYear::SomeYear(2020), Semester::FirstTerm)
+        .snapshot_path(&path)
+        .sink(ChannelSink::new(sender));
+    let events = watcher.check(&client).await.unwrap();
+    let messages: Vec<String> = events.iter().map(ToString::to_string).collect();
+    assert_eq!(
+        messages,
+        vec!["Score changed: 大学物理A1 86 -> 90", "New score: 军训 合格"]
+    );
+    assert!(receiver.recv().await.is_some());
+    assert!(receiver.recv().await.is_some());
+    assert!(receiver.try_recv().is_err());
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn test_watcher_stops_on_wrong_password() {
+    use crate::mock::MockServer;
+    use crate::session::SessionBuilder;
+    use crate::ZfError;
+
+    let server = MockServer::start("2018000000", "passwd").await;
+    let client = SessionBuilder::new()
+        .user("2018000000")
+        .passwd("passwd")
+        .base_url(server.base_url())
+        .build()
+        .login()
+        .await
+        .unwrap();
+    let mut watcher = ScoreWatcher::new(SchoolYear::SomeYear(2020), Semester::FirstTerm)
+        .interval(Duration::from_millis(10));
+    watcher.check(&client).await.unwrap();
+
+    // The password is changed on another device while watching.
+    server.set_passwd("changed");
+    server.expire_sessions();
+    let result = tokio::time::timeout(Duration::from_secs(5), watcher.run(&client))
+        .await
+        .expect("The watcher keeps retrying with the wrong password");
+    match result {
+        Err(ZfError::SessionExpired(Some(e))) => {
+            assert!(matches!(*e, ZfError::AuthenticationFailed(_)))
+        }
+        r => panic!("Unexpected result: {:?}", r),
+    }
+    assert_eq!(server.login_attempts(), 2);
+}
@@ -0,0 +1,240 @@
+use crate::watch::ScoreEvent;
+use crate::{Result, ZfError};
+use async_trait::async_trait;
+use base64::encode;
+use chrono::Local;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc::UnboundedSender;
+
+/// Where the watcher sends score events.
+#[async_trait]
+pub trait Sink: Send + Sync {
+    async fn notify(&self, events: &[ScoreEvent]) -> Result<()>;
+}
+
+/// Print events to stdout, one per line.
+pub struct StdoutSink;
+
+/// Send events to a channel, the receiver works as a stream of events.
+pub struct ChannelSink {
+    sender: UnboundedSender<ScoreEvent>,
+}
+
+/// POST events to the url as a JSON list.
+pub struct WebhookSink {
+    url: String,
+    client: reqwest::Client,
+}
+
+/// Send events by email through an SMTP relay without authentication, like a local postfix.
+pub struct SmtpSink {
+    /// Relay address, like `127.0.0.1:25`
+    relay: String,
+    from: String,
+    to: Vec<String>,
+    timeout: Duration,
+}
+
+#[async_trait]
+impl Sink for StdoutSink {
+    async fn notify(&self, events: &[ScoreEvent]) -> Result<()> {
+        events.iter().for_each(|event| println!("{}", event));
+        Ok(())
+    }
+}
+
+impl ChannelSink {
+    pub fn new(sender: UnboundedSender<ScoreEvent>) -> Self {
+        ChannelSink { sender }
+    }
+}
+
+#[async_trait]
+impl Sink for ChannelSink {
+    async fn notify(&self, events: &[ScoreEvent]) -> Result<()> {
+        for event in events {
+            // Nobody cares if the receiver is dropped.
+            self.sender.send(event.clone()).ok();
+        }
+        Ok(())
+    }
+}
+
+fn webhook_client(timeout: Duration) -> reqwest::Client {
+    reqwest::Client::builder()
+        .connect_timeout(std::cmp::min(timeout, Duration::from_secs(10)))
+        .timeout(timeout)
+        .build()
+        .unwrap_or_else(|e| panic!("Failed to build the HTTP client: {}", e))
+}
+
+impl WebhookSink {
+    pub fn new<T: ToString>(url: T) -> Self {
+        WebhookSink {
+            url: url.to_string(),
+            client: webhook_client(Duration::from_secs(30)),
+        }
+    }
+
+    /// Timeout of a notification, defaults to 30 seconds, so that a hung webhook does not block
+    /// the watcher.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.client = webhook_client(timeout);
+        self
+    }
+}
+
+#[async_trait]
+impl Sink for WebhookSink {
+    async fn notify(&self, events: &[ScoreEvent]) -> Result<()> {
+        let response = self.client.post(&self.url).json(events).send().await?;
+        if !response.status().is_success() {
+            return Err(ZfError::HttpStatus {
+                status: response.status(),
+                url: self.url.clone(),
+            });
+        }
+        Ok(())
+    }
+}
+
+fn smtp_error(message: String) -> ZfError {
+    ZfError::Notify(message)
+}
+
+/// Read a reply, which may take multiple lines like "250-..." and ends with "250 ...".
+async fn read_reply<R: AsyncBufReadExt + Unpin>(reader: &mut R, expected: char) -> Result<()> {
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).await? == 0 {
+            return Err(smtp_error("SMTP connection closed".to_string()));
+        }
+        if !line.starts_with(expected) {
+            return Err(smtp_error(format!("SMTP error: {}", line.trim_end())));
+        }
+        if line.as_bytes().get(3) != Some(&b'-') {
+            return Ok(());
+        }
+    }
+}
+
+impl SmtpSink {
+    pub fn new<T: ToString>(relay: T, from: T, to: &[T]) -> Self {
+        SmtpSink {
+            relay: relay.to_string(),
+            from: from.to_string(),
+            to: to.iter().map(ToString::to_string).collect(),
+            timeout: Duration::from_secs(30),
+        }
+    }
+
+    /// Timeout of a notification, including the connection and the whole dialogue, defaults to
+    /// 30 seconds, so that a hung relay does not block the watcher.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    fn message(&self, events: &[ScoreEvent]) -> String {
+        // Lines starting with "." are escaped as "..", see RFC 5321 section 4.5.2.
+        let body: Vec<String> = events
+            .iter()
+            .flat_map(|event| {
+                let text = event.to_string();
+                text.lines()
+                    .map(|line| {
+                        if line.starts_with('.') {
+                            format!(".{}", line)
+                        } else {
+                            line.to_string()
+                        }
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        let body = body.join("\r\n");
+        format!(
+            "From: {}\r\nTo: {}\r\nSubject: =?UTF-8?B?{}?=\r\nDate: {}\r\n\
+             MIME-Version: 1.0\r\nContent-Type: text/plain; charset=UTF-8\r\n\
+             Content-Transfer-Encoding: 8bit\r\n\r\n{}\r\n.\r\n",
+            self.from,
+            self.to.join(", "),
+            encode(format!("{} score update(s)", events.len())),
+            Local::now().to_rfc2822(),
+            body
+        )
+    }
+
+    /// Send the message through the relay, see RFC 5321 section 3.3.
+    async fn send(&self, events: &[ScoreEvent]) -> Result<()> {
+        let stream = TcpStream::connect(&self.relay).await?;
+        let (reader, mut writer) = stream.into_split();
+        let mut reader = BufReader::new(reader);
+
+        read_reply(&mut reader, '2').await?;
+        let mut commands = vec![
+            ("HELO localhost\r\n".to_string(), '2'),
+            (format!("MAIL FROM:<{}>\r\n", self.from), '2'),
+        ];
+        commands.extend(
+            self.to
+                .iter()
+                .map(|to| (format!("RCPT TO:<{}>\r\n", to), '2')),
+        );
+        commands.push(("DATA\r\n".to_string(), '3'));
+        commands.push((self.message(events), '2'));
+        commands.push(("QUIT\r\n".to_string(), '2'));
+
+        for (command, expected) in commands {
+            writer.write_all(command.as_bytes()).await?;
+            read_reply(&mut reader, expected).await?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Sink for SmtpSink {
+    async fn notify(&self, events: &[ScoreEvent]) -> Result<()> {
+        tokio::time::timeout(self.timeout, self.send(events))
+            .await
+            .map_err(|_| smtp_error(format!("SMTP relay {} timed out", self.relay)))?
+    }
+}
+
+#[tokio::test]
+async fn test_webhook_timeout() {
+    // A webhook which accepts the connection but never responds.
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/hook", listener.local_addr().unwrap());
+    let server = tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        tokio::time::sleep(Duration::from_secs(60)).await;
+        drop(stream);
+    });
+
+    let sink = WebhookSink::new(url).timeout(Duration::from_millis(100));
+    let result = tokio::time::timeout(Duration::from_secs(5), sink.notify(&[])).await;
+    assert!(matches!(result, Ok(Err(ZfError::Transport(_)))));
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_smtp_timeout() {
+    // A relay which accepts the connection but never greets.
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let relay = listener.local_addr().unwrap().to_string();
+    let server = tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        tokio::time::sleep(Duration::from_secs(60)).await;
+        drop(stream);
+    });
+
+    let sink = SmtpSink::new(relay.as_str(), "zf@localhost", &["me@localhost"])
+        .timeout(Duration::from_millis(100));
+    let result = tokio::time::timeout(Duration::from_secs(5), sink.notify(&[])).await;
+    assert!(matches!(result, Ok(Err(ZfError::Notify(_)))));
+    server.abort();
+}
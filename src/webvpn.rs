@@ -0,0 +1,272 @@
+//! Access the server through the WebVPN gateway off campus.
+//!
+//! The gateway (网瑞达 WebVPN, used by SIT and many other schools) serves the sites in the campus
+//! network under its own host, with the host of the site encrypted into the path, like
+//! `https://webvpn.example.edu.cn/http-8080/77726476.../jwglxt/xtgl/login_slogin.html`. Set a
+//! `WebVpn` on `SessionBuilder::webvpn`, then the session logs in the gateway portal before the
+//! server, and sends all the requests through the gateway.
+
+use crate::session::Session;
+use crate::{Result, ZfError};
+use aes::cipher::generic_array::GenericArray;
+use aes::cipher::{BlockEncrypt, KeyInit};
+use aes::Aes128;
+use reqwest::Url;
+use serde::Deserialize;
+
+/// Key and IV of the host encryption, which are the same on all the gateways.
+const KEY: &[u8; 16] = b"wrdvpnisthebest!";
+
+/// Login page of the gateway portal
+const PORTAL_LOGIN: &str = "login";
+/// Login form of the gateway portal
+const PORTAL_DO_LOGIN: &str = "do-login";
+
+/// AES-128-CFB with full block feedback, which works as a stream cipher on the host.
+fn cfb(data: &[u8], decrypt: bool) -> Vec<u8> {
+    let cipher = Aes128::new(GenericArray::from_slice(KEY));
+    let mut feedback = GenericArray::clone_from_slice(KEY);
+    let mut output = Vec::with_capacity(data.len());
+    for block in data.chunks(16) {
+        let mut keystream = feedback;
+        cipher.encrypt_block(&mut keystream);
+        let result: Vec<u8> = block.iter().zip(&keystream).map(|(a, b)| a ^ b).collect();
+        // Only full blocks are followed by another block.
+        if block.len() == 16 {
+            feedback.copy_from_slice(if decrypt { block } else { &result });
+        }
+        output.extend(result);
+    }
+    output
+}
+
+fn to_hex(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(text: &str) -> Option<Vec<u8>> {
+    if text.len() % 2 != 0 {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Path segment of the host, which is the hex of the IV and the encrypted host.
+pub fn encrypt_host(host: &str) -> String {
+    format!("{}{}", to_hex(KEY), to_hex(&cfb(host.as_bytes(), false)))
+}
+
+/// Host of the path segment, `None` if it is not an encrypted host.
+pub fn decrypt_host(segment: &str) -> Option<String> {
+    let data = from_hex(segment.strip_prefix(&to_hex(KEY))?)?;
+    String::from_utf8(cfb(&data, true)).ok()
+}
+
+/// WebVPN gateway, see the module document.
+#[derive(Debug, Clone)]
+pub struct WebVpn {
+    /// Gateway address, always ends with `/`
+    gateway: Url,
+    /// Account of the portal, the same as the session if `None`
+    user: Option<String>,
+    passwd: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct PortalResult {
+    success: bool,
+    #[serde(default)]
+    message: String,
+    #[serde(default)]
+    error: String,
+}
+
+impl WebVpn {
+    /// Gateway address, like `https://webvpn.example.edu.cn/`.
+    pub fn new(gateway: &str) -> Result<Self> {
+        let mut gateway = Url::parse(gateway)?;
+        if !gateway.path().ends_with('/') {
+            gateway.set_path(&format!("{}/", gateway.path()));
+        }
+        Ok(WebVpn {
+            gateway,
+            user: None,
+            passwd: None,
+        })
+    }
+
+    /// Login the portal with another account instead of the one of the session.
+    pub fn credentials<T: ToString>(mut self, user: T, passwd: T) -> Self {
+        self.user = Some(user.to_string());
+        self.passwd = Some(passwd.to_string());
+        self
+    }
+
+    fn is_gateway(&self, url: &Url) -> bool {
+        url.host_str() == self.gateway.host_str()
+            && url.port_or_known_default() == self.gateway.port_or_known_default()
+    }
+
+    /// URL of the site through the gateway. URLs on the gateway are returned as they are.
+    pub fn rewrite(&self, url: &Url) -> Result<Url> {
+        if self.is_gateway(url) {
+            return Ok(url.clone());
+        }
+        let scheme = match url.port() {
+            Some(port) => format!("{}-{}", url.scheme(), port),
+            None => url.scheme().to_string(),
+        };
+        let host = encrypt_host(url.host_str().unwrap_or_default());
+        let path = url.path().trim_start_matches('/');
+        let mut result = self
+            .gateway
+            .join(&format!("{}/{}/{}", scheme, host, path))?;
+        result.set_query(url.query());
+        Ok(result)
+    }
+
+    /// Original URL of the site, `None` if the URL is not a site through the gateway.
+    pub fn restore(&self, url: &Url) -> Option<Url> {
+        if !self.is_gateway(url) {
+            return None;
+        }
+        let path = url.path().strip_prefix(self.gateway.path())?;
+        let mut segments = path.splitn(3, '/');
+        let scheme = segments.next()?;
+        let host = decrypt_host(segments.next()?)?;
+        let path = segments.next().unwrap_or_default();
+        let origin = match scheme.split_once('-') {
+            Some((scheme, port)) => format!("{}://{}:{}", scheme, host, port),
+            None => format!("{}://{}", scheme, host),
+        };
+        let mut result = Url::parse(&format!("{}/{}", origin, path)).ok()?;
+        result.set_query(url.query());
+        Some(result)
+    }
+
+    /// Whether the URL is the portal login page, where the gateway redirects to after the portal
+    /// session expired.
+    pub(crate) fn is_portal_login(&self, url: &Url) -> bool {
+        self.gateway
+            .join(PORTAL_LOGIN)
+            .is_ok_and(|login| self.is_gateway(url) && url.path() == login.path())
+    }
+
+    /// Login the portal, and keep the cookies of the gateway in the session.
+    pub(crate) async fn login(&self, session: &Session) -> Result<()> {
+        let user = self.user.as_deref().unwrap_or(&session.user);
+        let passwd = match &self.passwd {
+            Some(passwd) => passwd,
+            None => session.passwd()?,
+        };
+
+        let login_page = session
+            .execute(session.client.get(self.gateway.join(PORTAL_LOGIN)?))
+            .await?;
+        if !login_page.status().is_success() {
+            return Err(ZfError::HttpStatus {
+                status: login_page.status(),
+                url: login_page.url().to_string(),
+            });
+        }
+
+        let params = [
+            ("auth_type", "local"),
+            ("username", user),
+            ("password", passwd),
+            ("sms_code", ""),
+            ("captcha", ""),
+            ("needCaptcha", "false"),
+        ];
+        let request = session
+            .client
+            .post(self.gateway.join(PORTAL_DO_LOGIN)?)
+            .form(&params);
+        let text = session.execute(request).await?.text().await?;
+        let result: PortalResult =
+            serde_json::from_str(&text).map_err(|e| ZfError::from(e).at(PORTAL_DO_LOGIN))?;
+        if !result.success {
+            let reason = if result.message.is_empty() {
+                result.error
+            } else {
+                result.message
+            };
+            return Err(ZfError::AuthenticationFailed(format!("WebVPN: {}", reason)));
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_host_encryption() {
+    assert_eq!(
+        encrypt_host("jwxt.sit.edu.cn"),
+        "77726476706e69737468656265737421fae0598869236144300d8db9d6562d"
+    );
+    // Longer than a block
+    assert_eq!(
+        encrypt_host("jwxt.sit.edu.cn.example"),
+        "77726476706e69737468656265737421fae0598869236144300d8db9d6562d7bdccd5cc90051f5"
+    );
+    assert_eq!(
+        decrypt_host(&encrypt_host("jwxt.sit.edu.cn.example")).unwrap(),
+        "jwxt.sit.edu.cn.example"
+    );
+
+    let vpn = WebVpn::new("https://webvpn.example.edu.cn").unwrap();
+    let url =
+        Url::parse("http://jwxt.sit.edu.cn:8080/jwglxt/xtgl/login_slogin.html?time=1").unwrap();
+    let rewritten = vpn.rewrite(&url).unwrap();
+    assert_eq!(
+        rewritten.as_str(),
+        "https://webvpn.example.edu.cn/http-8080/77726476706e69737468656265737421fae0598869236144300d8db9d6562d/jwglxt/xtgl/login_slogin.html?time=1"
+    );
+    assert_eq!(vpn.restore(&rewritten).unwrap(), url);
+    assert_eq!(vpn.rewrite(&rewritten).unwrap(), rewritten);
+}
+
+#[tokio::test]
+async fn test_webvpn() {
+    use crate::client::User;
+    use crate::mock::{MockGateway, MockServer};
+    use crate::parsers::{SchoolYear, Semester};
+    use crate::session::SessionBuilder;
+
+    let server = MockServer::start("2018000000", "passwd").await;
+    let gateway = MockGateway::start("2018000000", "passwd").await;
+    let session = |webvpn: WebVpn| {
+        SessionBuilder::new()
+            .user("2018000000")
+            .passwd("passwd")
+            .base_url(server.base_url())
+            .webvpn(webvpn)
+            .build()
+    };
+
+    let webvpn = WebVpn::new(&gateway.base_url()).unwrap();
+    let client = session(webvpn.clone()).login().await.unwrap();
+    assert_eq!(client.get_profile().await.unwrap().name(), "王小明");
+    assert_eq!(gateway.login_count(), 1);
+    assert_eq!(server.login_count(), 1);
+
+    // Login the portal and the server again after the portal session expired.
+    gateway.expire_sessions();
+    let scores = client
+        .get_score_list(SchoolYear::AllYear, Semester::All)
+        .await
+        .unwrap();
+    assert_eq!(scores.len(), 2);
+    assert_eq!(gateway.login_count(), 2);
+    assert_eq!(server.login_count(), 2);
+
+    let result = session(webvpn.credentials("2018000000", "wrong"))
+        .login()
+        .await;
+    match result {
+        Err(ZfError::AuthenticationFailed(message)) => assert!(message.starts_with("WebVPN")),
+        r => panic!("Unexpected login result: {:?}", r.map(|_| ())),
+    }
+}